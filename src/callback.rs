@@ -0,0 +1,159 @@
+use log::LevelFilter;
+use std::{fmt, sync::mpsc::Sender};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::DSink;
+
+/// Closure called by [`DCallback`].
+type DCallbackFn = Box<dyn Fn(&DRecord, &str) + Send + Sync>;
+
+/// Sink that calls a closure for each record, with the structured record and the formatted line.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{DLog, DCallback, LevelFilter};
+/// 
+/// let durylog=DLog::new()
+///     .with_sink(
+///         DCallback::new(|record, line| eprintln!("[status bar] {} ({})", line, record.level))
+///             .with_level(LevelFilter::Warn)
+///     );
+/// ```
+pub struct DCallback {
+    /// User closure
+    callback: DCallbackFn,
+    /// Max level passed to closure
+    level: LevelFilter,
+}
+
+impl DCallback {
+    /// Create a sink that calls ['callback'] with the record and its formatted line.
+    pub fn new<F: Fn(&DRecord, &str) + Send + Sync + 'static>(callback: F) -> Self {
+        Self {
+            callback: Box::new(callback),
+            level: LevelFilter::Trace,
+        }
+    }
+
+    /// Call closure only for records up to ['level']. Default is all records.
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level=level;
+        self
+    }
+}
+
+impl fmt::Debug for DCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DCallback").field("level", &self.level).finish_non_exhaustive()
+    }
+}
+
+impl DSink for DCallback {
+    fn write(&self, record: &DRecord, line: &str) -> Result<(), DLogError> {
+        if record.level <= self.level {
+            (self.callback)(record, line);
+        }
+        Ok(())
+    }
+}
+
+/// Receiving side of [`DChannel`].
+#[derive(Debug)]
+enum DChannelSender {
+    /// Sends structured records
+    Records(Sender<DRecord>),
+    /// Sends formatted lines
+    Lines(Sender<String>),
+}
+
+/// Sink that sends records, or formatted lines, into a `std::sync::mpsc` channel.
+/// 
+/// Records are dropped if the receiver has been dropped.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{DLog, DChannel, LevelFilter};
+/// use std::sync::mpsc;
+/// 
+/// let (sender, receiver)=mpsc::channel();
+/// let durylog=DLog::new().without_console().with_sink(DChannel::new(sender).with_level(LevelFilter::Warn));
+/// 
+/// durylog.w("low battery");
+/// durylog.i("not sent");
+/// assert_eq!(receiver.try_recv().unwrap().message, "low battery");
+/// assert!(receiver.try_recv().is_err());
+/// ```
+#[derive(Debug)]
+pub struct DChannel {
+    /// Channel
+    sender: DChannelSender,
+    /// Max level sent
+    level: LevelFilter,
+}
+
+impl DChannel {
+    /// Create a sink that sends structured records to ['sender'].
+    pub fn new(sender: Sender<DRecord>) -> Self {
+        Self {
+            sender: DChannelSender::Records(sender),
+            level: LevelFilter::Trace,
+        }
+    }
+
+    /// Create a sink that sends formatted lines to ['sender'].
+    pub fn formatted(sender: Sender<String>) -> Self {
+        Self {
+            sender: DChannelSender::Lines(sender),
+            level: LevelFilter::Trace,
+        }
+    }
+
+    /// Send only records up to ['level']. Default is all records.
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level=level;
+        self
+    }
+}
+
+impl DSink for DChannel {
+    fn write(&self, record: &DRecord, line: &str) -> Result<(), DLogError> {
+        if record.level > self.level {
+            return Ok(());
+        }
+        let sent=match &self.sender {
+            DChannelSender::Records(sender) => sender.send(record.clone()).is_ok(),
+            DChannelSender::Lines(sender) => sender.send(line.to_string()).is_ok(),
+        };
+        if sent {
+            Ok(())
+        } else {
+            Err(DLogError::Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "channel receiver dropped")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DLog;
+    use std::sync::{mpsc, Arc, Mutex};
+
+    #[test]
+    fn callback_level_filter() {
+        let received=Arc::new(Mutex::new(Vec::new()));
+        let cloned=received.clone();
+        let durylog=DLog::new().without_console().with_timestamp_format("T")
+            .with_sink(DCallback::new(move |_, line| cloned.lock().unwrap().push(line.to_string())).with_level(LevelFilter::Info));
+        durylog.i("shown");
+        durylog.d("hidden");
+        assert_eq!(*received.lock().unwrap(), ["T : INFO   : shown"]);
+    }
+
+    #[test]
+    fn formatted_channel() {
+        let (sender, receiver)=mpsc::channel();
+        let durylog=DLog::new().without_console().with_timestamp_format("T").with_sink(DChannel::formatted(sender));
+        durylog.e("failed");
+        assert_eq!(receiver.try_recv().unwrap(), "T : ERROR  : failed");
+    }
+}
@@ -0,0 +1,25 @@
+use std::time::Instant;
+#[cfg(test)]
+use std::{cell::Cell, time::Duration};
+
+#[cfg(test)]
+thread_local! {
+    /// Time added to the real clock on this thread by tests
+    static OFFSET: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// ['return'] current instant, used by time based settings (duplicates suppression, rate limits).
+/// 
+/// In tests it can be moved forward on current thread with ['advance()'].
+pub(crate) fn now() -> Instant {
+    #[cfg(test)]
+    return Instant::now() + OFFSET.with(Cell::get);
+    #[cfg(not(test))]
+    Instant::now()
+}
+
+/// Move clock of current thread forward by ['duration'].
+#[cfg(test)]
+pub(crate) fn advance(duration: Duration) {
+    OFFSET.with(|offset| offset.set(offset.get() + duration));
+}
@@ -0,0 +1,81 @@
+use std::{cell::{Cell, RefCell}, fmt, marker::PhantomData};
+
+thread_local! {
+    /// Fields of current thread: (id, key, value), oldest first.
+    static CONTEXT: RefCell<Vec<(u64, String, String)>> = const { RefCell::new(Vec::new()) };
+    /// Id of next pushed field.
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Guard returned by [`push()`]: the field is removed from current thread context when dropped.
+#[derive(Debug)]
+#[must_use = "the field is removed when the guard is dropped"]
+pub struct DContextGuard {
+    /// Id of the pushed field
+    id: u64,
+    /// Guard is bound to the thread that created it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for DContextGuard {
+    fn drop(&mut self) {
+        CONTEXT.with(|context| context.borrow_mut().retain(|(id, _, _)| *id != self.id));
+    }
+}
+
+/// Add field ['key']=['value'] to every record emitted on current thread (by std::log macros or direct
+/// api), until the returned guard is dropped.
+/// 
+/// If the same key is pushed more than once, the last pushed value is used.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{context, DLog};
+/// 
+/// let durylog=DLog::new();
+/// {
+///     let _guard=context::push("request_id", 42);
+///     durylog.i("Request received"); // 2023/01/02 18.01.27 : INFO   : Request received request_id=42
+/// }
+/// durylog.i("Idle"); // 2023/01/02 18.01.27 : INFO   : Idle
+/// ```
+pub fn push<V: fmt::Display>(key: &str, value: V) -> DContextGuard {
+    let id=NEXT_ID.with(|next| {
+        next.set(next.get() + 1);
+        next.get()
+    });
+    CONTEXT.with(|context| context.borrow_mut().push((id, key.to_string(), value.to_string())));
+    DContextGuard {
+        id,
+        _not_send: PhantomData,
+    }
+}
+
+/// ['return'] fields of current thread context.
+pub fn fields() -> Vec<(String, String)> {
+    CONTEXT.with(|context| {
+        let context=context.borrow();
+        context.iter().enumerate()
+            .filter(|(i, (_, key, _))| !context[i + 1..].iter().any(|(_, k, _)| k == key))
+            .map(|(_, (_, key, value))| (key.clone(), value.clone()))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guards_scope_fields() {
+        let outer=push("request_id", 1);
+        {
+            let _user=push("user", "bob");
+            let _override=push("request_id", 2);
+            assert_eq!(fields(), [("user".to_string(), "bob".to_string()), ("request_id".to_string(), "2".to_string())]);
+        }
+        assert_eq!(fields(), [("request_id".to_string(), "1".to_string())]);
+        drop(outer);
+        assert!(fields().is_empty());
+    }
+}
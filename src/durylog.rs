@@ -1,33 +1,54 @@
 //#![allow(missing_docs)]
 #[doc(inline)]
-use chrono::Utc;
-pub use log::{debug, error, info, trace, warn, LevelFilter};
-use log::{Level, Log, Metadata, ParseLevelError, Record, SetLoggerError};
+use chrono::{DateTime, Utc};
+pub use log::{debug, error, info, trace, warn, Level, LevelFilter};
+use log::{Log, Metadata, ParseLevelError, Record, SetLoggerError, kv};
+use crate::redact::DRedactor;
+use crate::limit::DRateLimiter;
+use crate::sink::{DSink, record_json, record_logfmt};
+use crate::context;
+use crate::timer::DTimer;
+use crate::file::{DFile, DFileOptions, DFileRoute};
+use crate::panic_hook;
+use crate::clock;
 use std::{
     env::{self, VarError},
-    str::FromStr, fs::{OpenOptions, File, self}, io::{self, Write, stdout}, path::PathBuf,
+    str::FromStr, io::{self, Write, stdout}, fmt,
+    sync::{Arc, Mutex, OnceLock, RwLock}, time::{Duration, Instant},
+    collections::VecDeque, thread, panic::Location,
 };
 
 /// Colors
 const COLOR_RED:     &str = "\x1B[38;5;196m";
 //const COLOR_GREEN:   &str = "\x1B[38,5,2m";
+/// Trace color
 const COLOR_LIME:    &str = "\x1B[38;5;10m";
+/// Warn color
 const COLOR_YELLOY:  &str = "\x1B[38;5;11m";
 //const COLOR_BLUE:    &str = "\x1B[38;5;4m";
 //const COLOR_MAGENTA: &str = "\x1B[38;5;13m";
+/// Debug color
 const COLOR_CYAN:    &str = "\x1B[38;5;14m";
+/// Reset color (Info)
 const COLOR_DEFAULT: &str = "\x1B[0m";
 
-/// Log levels strings
-const STR_ERROR: &str = "ERROR ";
-const STR_WARN:  &str = "WARN  ";
-const STR_INFO:  &str = "INFO  ";
-const STR_DEBUG: &str = "DEBUG ";
-const STR_TRACE: &str = "TRACE ";
+/// Default level labels (Error, Warn, Info, Debug, Trace).
+pub const LEVEL_LABELS_DEFAULT: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+/// Lowercase level labels.
+pub const LEVEL_LABELS_LOWER: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+/// Single letter level labels.
+pub const LEVEL_LABELS_SHORT: [&str; 5] = ["E", "W", "I", "D", "T"];
+/// Full word level labels.
+pub const LEVEL_LABELS_FULL: [&str; 5] = ["ERROR", "WARNING", "INFORMATION", "DEBUG", "TRACE"];
 
 /// Default settings values
 const DEFAULT_TIMESTAMP_FORMAT: &str="%Y/%m/%d %H.%M.%S";
+/// Default tags separator
 const DEFAULT_SEP: &str = " : ";
+/// Default level tag width (keeps the historical "ERROR " / "WARN  " layout)
+const DEFAULT_LEVEL_WIDTH: usize = 6;
+/// Max interval between checks of time based lines (see ['DSettings::tick_interval()'])
+const MAX_TICK_INTERVAL: Duration = Duration::from_secs(1);
 //const MB: u64 = 1024 * 1024;
 
 /// Enumaration to handle different kinds of errors.
@@ -40,8 +61,58 @@ pub enum DLogError {
     #[doc(hidden)]
     Err(io::Error),
     #[doc(hidden)]
+    Regex(regex::Error),
+    #[doc(hidden)]
+    Overflow,
+    #[doc(hidden)]
     None,
 }
+
+/// Alignment of the level tag inside its column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DLevelAlign {
+    /// Pad on the right: `"WARN  "` (default).
+    Left,
+    /// Pad on the left: `" WARN "`.
+    Right,
+    /// No padding: `"WARN"`.
+    None,
+}
+
+/// How to handle messages that contain newlines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DMultiline {
+    /// Write message as is: prefix only on first line (default).
+    Keep,
+    /// Repeat the prefix (timestamp and level) on each line.
+    Repeat,
+    /// Indent continuation lines to the width of the prefix.
+    Indent,
+    /// Escape newlines as literal `\n`, so each message is on a single line.
+    Escape,
+}
+
+/// Format of log lines on console, file and additional outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DFormat {
+    /// Tags and message separated by separator, fields as ` key=value` (default).
+    Text,
+    /// One json object per line.
+    Json,
+    /// One logfmt line (`time=... level=info msg="..." key=value`).
+    Logfmt,
+}
+
+/// How control characters and ANSI escape sequences in messages and key-value fields are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DSanitize {
+    /// Write content verbatim (default).
+    Off,
+    /// Escape control characters: newline becomes `\n`, ESC becomes `\x1b` and so on.
+    Escape,
+    /// Remove ANSI escape sequences and control characters (newlines and tabs become spaces).
+    Strip,
+}
 /*
 enum DStorageMode {
     BySize,
@@ -50,26 +121,179 @@ enum DStorageMode {
 */
 
 /// struct to hold all settings to handle logging.
+/// 
+/// Settings and outputs are shared with child loggers (see ['child()']): builder functions (`with_*()`) and
+/// `enable_*()` functions can be called at any time, also after child loggers are created or logging has
+/// started, and apply to all of them.
 #[derive(Debug)]
 pub struct DLog {
+    /// Settings and outputs, shared with child loggers
+    state: Arc<DLogState>,
+}
+
+/// Settings, outputs and runtime state of a [`DLog`], shared with its child loggers.
+#[derive(Debug)]
+struct DLogState {
+    /// Current settings: a record is written with the copy taken when it is logged
+    settings: RwLock<Arc<DSettings>>,
+    /// Last written record and how many times it has been repeated since
+    dedup_state: Mutex<Option<DDedupState>>,
+    /// Thread writing time based lines, started by first record that needs it
+    ticker: OnceLock<()>,
+    /// Recent records filtered out by level, dumped on error or panic
+    flight_recorder: Mutex<VecDeque<DRecord>>,
+}
+
+/// Settings and outputs of a [`DLog`].
+#[derive(Debug, Clone)]
+struct DSettings {
+    /// Most verbose level written
     level: LevelFilter,
+    /// Target prefix of records written (all if None)
     target: Option<String>,
 
+    /// Use colors on console
     show_color_enabled: bool,
+    /// Write on stdout
     log_on_stdout: bool,
 
     // File params
+    /// Write in main file
     log_on_file: bool,
-    filename: PathBuf,
-    file: Option<File>,
+    /// Main file
+    file: Option<Arc<DFile>>,
+    /// Max size of main file before rotation (0 = no limit)
     max_file_size: u64,
+    /// Max number of main files to keep (0 = no limit)
     max_files_count: u64,
+    /// Files for records selected by level and/or target
+    routes: Vec<(DFileRoute, Arc<DFile>)>,
+    /// Creation options of log files
+    file_options: DFileOptions,
 
     // Formatting message flags
+    /// Timestamp format (chrono)
     timestamp_format: String,
+    /// Show timestamp tag
     show_timestamp_enabled: bool,
+    /// Show level tag
     show_level_enabled: bool,
+    /// Show target tag
+    show_target_enabled: bool,
+    /// Separator between tags
     separator: String,
+    /// Level labels (Error, Warn, Info, Debug, Trace)
+    level_labels: [String; 5],
+    /// Level tag alignment
+    level_align: DLevelAlign,
+    /// Level tag column width
+    level_width: usize,
+    /// Enclose level label in brackets
+    level_brackets_enabled: bool,
+    /// Lines format
+    format: DFormat,
+    /// Multi-line messages policy
+    multiline: DMultiline,
+    /// Sanitize policy for console output
+    console_sanitize: DSanitize,
+    /// Sanitize policy for file output
+    file_sanitize: DSanitize,
+    /// Secrets redaction rules
+    redactor: Option<Arc<DRedactor>>,
+
+    // Duplicates suppression
+    /// Collapse consecutive identical records
+    dedup_enabled: bool,
+    /// Max time to wait before writing the "repeated" line (zero = wait for a different message)
+    dedup_timeout: Duration,
+    /// Rate limits and sampling rules
+    rate_limiter: Option<Arc<DRateLimiter>>,
+
+    /// Additional outputs
+    sinks: Vec<Arc<dyn DSink>>,
+
+    /// Max records kept by flight recorder (0 = disabled)
+    flight_recorder_size: usize,
+
+    // Panic hook
+    /// Install panic hook in ['init_logger()']
+    panic_hook_enabled: bool,
+    /// Add backtrace to panic records
+    panic_backtrace_enabled: bool,
+}
+
+/// Last record written, for duplicates suppression.
+#[derive(Debug)]
+struct DDedupState {
+    /// Level of last record
+    level: Level,
+    /// Target of last record
+    target: String,
+    /// Message of last record
+    message: String,
+    /// Number of suppressed repetitions
+    count: u64,
+    /// When repetitions counting started
+    since: Instant,
+}
+
+/// A single log entry, as it goes through the logging pipeline.
+#[derive(Debug, Clone)]
+pub struct DRecord {
+    /// Creation time
+    pub timestamp: DateTime<Utc>,
+    /// Level
+    pub level: Level,
+    /// Target (module path for log macros, empty for direct api)
+    pub target: String,
+    /// Source file
+    pub file: Option<String>,
+    /// Source line
+    pub line: Option<u32>,
+    /// Message
+    pub message: String,
+    /// Key-value fields
+    pub fields: Vec<(String, String)>,
+}
+
+impl DRecord {
+    /// Create a record of ['level'] for ['message'] with no target and no fields.
+    pub fn new(level: Level, message: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            level,
+            target: String::new(),
+            file: None,
+            line: None,
+            message: message.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Create a record from a std::log ['Record'], collecting its key-values.
+    fn from_log(record: &Record) -> Self {
+        let mut collector=FieldsCollector(Vec::new());
+        record.key_values().visit(&mut collector).ok();
+        Self {
+            timestamp: Utc::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            file: record.file().map(String::from),
+            line: record.line(),
+            message: record.args().to_string(),
+            fields: collector.0,
+        }
+    }
+}
+
+/// Visitor that collects std::log key-values as strings.
+struct FieldsCollector(Vec<(String, String)>);
+
+impl<'kvs> kv::VisitSource<'kvs> for FieldsCollector {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
 }
 
 impl Default for DLog {
@@ -85,66 +309,186 @@ impl DLog {
     /// - Show Timestamp.
     /// - Show Level.
     pub fn new() -> Self {
-        Self {
+        let settings=DSettings {
             level: LevelFilter::Trace,
             target: None,
 
             show_color_enabled: false,
             log_on_stdout: true,
 
-            log_on_file:false,
-            filename: PathBuf::new(),
+            log_on_file: false,
             file: None,
             max_file_size: 0, // no limits
             max_files_count: 0, // no limits
+            routes: Vec::new(),
+            file_options: DFileOptions::default(),
 
             show_timestamp_enabled: true,
             timestamp_format: String::from(DEFAULT_TIMESTAMP_FORMAT),
             show_level_enabled: true,
+            show_target_enabled: false,
             separator: String::from(DEFAULT_SEP),
+            level_labels: LEVEL_LABELS_DEFAULT.map(String::from),
+            level_align: DLevelAlign::Left,
+            level_width: DEFAULT_LEVEL_WIDTH,
+            level_brackets_enabled: false,
+            format: DFormat::Text,
+            multiline: DMultiline::Keep,
+            console_sanitize: DSanitize::Off,
+            file_sanitize: DSanitize::Off,
+            redactor: None,
+
+            dedup_enabled: false,
+            dedup_timeout: Duration::ZERO,
+            rate_limiter: None,
+
+            sinks: Vec::new(),
+
+            flight_recorder_size: 0,
+
+            panic_hook_enabled: false,
+            panic_backtrace_enabled: false,
+        };
+        Self {
+            state: Arc::new(DLogState {
+                settings: RwLock::new(Arc::new(settings)),
+                dedup_state: Mutex::new(None),
+                ticker: OnceLock::new(),
+                flight_recorder: Mutex::new(VecDeque::new()),
+            }),
         }
     }
     
 // ************** Api for new() initialization **************
     /// Enable logging on file and open it.
-    pub fn with_file(mut self, filename: &str) -> Result<Self, DLogError> {
-        match self.open_file(filename) {
+    /// 
+    /// ['filename'] can contain placeholders, expanded when the file is opened and at each rotation:
+    /// - `{app}`: executable name.
+    /// - `{date}` or `{date:FORMAT}`: local date (chrono format, default `%Y%m%d`).
+    /// - `{pid}`: process id.
+    /// - `{hostname}`: host name.
+    /// 
+    /// e.g. `logs/{app}-{date:%Y%m%d}-{pid}.log`. When the date in file name changes, a new file is opened
+    /// (the old one is kept as is).
+    /// 
+    /// Rotation retention applies to archives (see ['with_max_file_size()']) of any name the template expands
+    /// to in the directory of current file: `{pid}` matches any process id and `{date}` any date of its format.
+    /// Other files, like current files of routes or of other processes, are never deleted.
+    /// 
+    /// [`DLog::with_file_mode()`] and [`DLog::with_file_lock()`] apply whenever they are called, while
+    /// [`DLog::with_create_dirs()`] must be called before this to create directories of the first file.
+    pub fn with_file(self, filename: &str) -> Result<Self, DLogError> {
+        match self.state.settings().open_file(filename) {
             Ok(file) => {
-                self.file=Some(file);
-                self.log_on_file=true;
-                self.filename=PathBuf::from(filename);
+                self.state.update(|settings| {
+                    settings.file=Some(Arc::new(file));
+                    settings.log_on_file=true;
+                });
                 Ok(self)
                 
             },
             Err(err) => {
-                self.file=None;
-                self.log_on_file=false;
-                self.filename.clear();
-                Err(DLogError::Err(err))
+                self.state.update(|settings| {
+                    settings.file=None;
+                    settings.log_on_file=false;
+                });
+                Err(err)
             }
         }
     }
 
+    /// Rotate log file when it exceeds ['max_file_size'] bytes (0 = no limit).
+    /// 
+    /// Rotated files are renamed as `<name>-<timestamp>.<extension>` in the same directory.
+    pub fn with_max_file_size(self, max_file_size: u64) -> Self {
+        self.state.update(|settings| {
+            settings.max_file_size=max_file_size;
+            settings.apply_rotation();
+        });
+        self
+    }
+
+    /// Keep at most ['max_files_count'] log files on rotation, current one included (0 = no limit).
+    pub fn with_max_files_count(self, max_files_count: u64) -> Self {
+        self.state.update(|settings| {
+            settings.max_files_count=max_files_count;
+            settings.apply_rotation();
+        });
+        self
+    }
+
+    /// Add a file route and open its file: records matching it are written in its file (see [`DFileRoute`]).
+    pub fn with_route(self, route: DFileRoute) -> Result<Self, DLogError> {
+        let file=route.open(self.state.settings().file_options)?;
+        self.state.update(|settings| settings.routes.push((route, Arc::new(file))));
+        Ok(self)
+    }
+
+    /// Create missing parent directories of log files when they are opened.
+    pub fn with_create_dirs(self) -> Self {
+        self.state.update(|settings| {
+            settings.file_options.create_dirs=true;
+            settings.apply_file_options();
+        });
+        self
+    }
+
+    /// Lock log files (advisory `flock`-like lock) around each write and rotation, so that several processes
+    /// can share the same rotating files.
+    /// 
+    /// Needs `file-lock` feature (Rust 1.89 or later). If the file system doesn't support locking, the error
+    /// is reported once on stderr and files are written without lock.
+    #[cfg(feature = "file-lock")]
+    pub fn with_file_lock(self) -> Self {
+        self.state.update(|settings| {
+            settings.file_options.lock=true;
+            settings.apply_file_options();
+        });
+        self
+    }
+
+    /// Set unix permission bits (e.g. `0o640`) of log files created by the logger, regardless of umask.
+    /// Rotated archives keep them; files that already exist are left untouched.
+    #[cfg(unix)]
+    pub fn with_file_mode(self, mode: u32) -> Self {
+        self.state.update(|settings| {
+            settings.file_options.mode=Some(mode);
+            settings.apply_file_options();
+        });
+        self
+    }
+
     /// Convenient function to enable color in construction.
     pub fn with_color(mut self) -> Self {
         self.enabled_colors(true);
         self
     }
 
-    /// Filter log target.
-    pub fn widh_target_filter<S: AsRef<str>>(mut self, target: S) -> Self {
+    /// Filter log target: only records whose target starts with ['target'] are written.
+    /// 
+    /// Records without target (direct api not scoped with ['target()']) always pass the target filter.
+    pub fn widh_target_filter<S: AsRef<str>>(self, target: S) -> Self {
         let target = target.as_ref().replace('-', "_");
-        self.target = Some(target);
+        self.state.update(|settings| settings.target=Some(target));
         self
     }
 
+    /// Same as ['widh_target_filter()'].
+    pub fn with_target_filter<S: AsRef<str>>(self, target: S) -> Self {
+        self.widh_target_filter(target)
+    }
 
     /// Filter log level.
     pub fn widh_level(&mut self, level: LevelFilter) -> &mut Self {
-        self.level = level;
+        self.state.update(|settings| settings.level=level);
         self
     }
 
+    /// Same as ['widh_level()'].
+    pub fn with_level(&mut self, level: LevelFilter) -> &mut Self {
+        self.widh_level(level)
+    }
+
     /// Filter log level from ['name'] environment variable.
     pub fn with_level_from_env<S: AsRef<str>>(self, name: S) -> Result<Self, DLogError> {
         match env::var(name.as_ref()) {
@@ -154,35 +498,169 @@ impl DLog {
     }
 
     /// Filter log level from `str`.
-    pub fn widh_level_from_str<S: AsRef<str>>(mut self, s: S) -> Result<Self, DLogError> {
+    pub fn widh_level_from_str<S: AsRef<str>>(self, s: S) -> Result<Self, DLogError> {
         match LevelFilter::from_str(s.as_ref()) {
             Ok(level) => {
-                self.level = level;
+                self.state.update(|settings| settings.level=level);
                 Ok(self)
             }
             Err(err) => Err(DLogError::Level(err)),
         }
     }
 
+    /// Same as ['widh_level_from_str()'].
+    pub fn with_level_from_str<S: AsRef<str>>(self, s: S) -> Result<Self, DLogError> {
+        self.widh_level_from_str(s)
+    }
+
     /// Use custom datetime stamp format.
-    pub fn widh_timestamp_format(mut self, format: &str) -> Self {
-        self.timestamp_format=String::from(format);
+    pub fn widh_timestamp_format(self, format: &str) -> Self {
+        self.state.update(|settings| settings.timestamp_format=String::from(format));
         self
     }
 
+    /// Same as ['widh_timestamp_format()'].
+    pub fn with_timestamp_format(self, format: &str) -> Self {
+        self.widh_timestamp_format(format)
+    }
+
     /// Use custom separator for tags. Default separator is ':'.
     /// 
     /// E.g.:
     /// 
     /// 2022/12/28 17.38.42 : ERROR  : Error message 
-    pub fn widh_custom_separator(mut self, new_sep: &str) -> Self{
-        self.separator=new_sep.to_string();
+    pub fn widh_custom_separator(self, new_sep: &str) -> Self{
+        self.state.update(|settings| settings.separator=new_sep.to_string());
+        self
+    }
+
+    /// Same as ['widh_custom_separator()'].
+    pub fn with_custom_separator(self, new_sep: &str) -> Self {
+        self.widh_custom_separator(new_sep)
+    }
+
+    /// Use custom labels for levels, in order: Error, Warn, Info, Debug, Trace.
+    /// 
+    /// Some presets are available: [`LEVEL_LABELS_SHORT`], [`LEVEL_LABELS_LOWER`], [`LEVEL_LABELS_FULL`].
+    /// 
+    /// Level tag column width is set to the longest label followed by a space, like the default
+    /// "ERROR " / "WARN  " layout.
+    pub fn with_level_labels<S: AsRef<str>>(self, labels: [S; 5]) -> Self {
+        let labels=labels.map(|l| l.as_ref().to_string());
+        self.state.update(|settings| {
+            settings.level_width=labels.iter().map(|l| l.chars().count()).max().unwrap_or_default() + 1;
+            settings.level_labels=labels;
+        });
+        self
+    }
+
+    /// Set alignment of level tag. Default is [`DLevelAlign::Left`].
+    pub fn with_level_align(self, align: DLevelAlign) -> Self {
+        self.state.update(|settings| settings.level_align=align);
+        self
+    }
+
+    /// Convenient function to enclose level label in brackets in construction.
+    /// 
+    /// E.g.:
+    /// 
+    /// 2022/12/28 17.38.42 : \[ERROR\] : Error message
+    pub fn with_level_brackets(mut self) -> Self {
+        self.enable_level_brackets(true);
+        self
+    }
+
+    /// Set format of log lines. Default is [`DFormat::Text`].
+    /// 
+    /// Json and logfmt lines always have an RFC 3339 timestamp, are always on a single line and are never colored.
+    pub fn with_format(self, format: DFormat) -> Self {
+        self.state.update(|settings| settings.format=format);
+        self
+    }
+
+    /// Set how messages containing newlines are written. Default is [`DMultiline::Keep`].
+    /// 
+    /// Applies to both console and file.
+    pub fn with_multiline(self, multiline: DMultiline) -> Self {
+        self.state.update(|settings| settings.multiline=multiline);
+        self
+    }
+
+    /// Sanitize control characters and ANSI sequences in messages and fields printed on console.
+    /// Default is [`DSanitize::Off`].
+    pub fn with_console_sanitize(self, sanitize: DSanitize) -> Self {
+        self.state.update(|settings| settings.console_sanitize=sanitize);
+        self
+    }
+
+    /// Sanitize control characters and ANSI sequences in messages and fields written in file.
+    /// Default is [`DSanitize::Off`].
+    pub fn with_file_sanitize(self, sanitize: DSanitize) -> Self {
+        self.state.update(|settings| settings.file_sanitize=sanitize);
+        self
+    }
+
+    /// Hide secrets in messages and fields using ['redactor'] rules, before any formatting.
+    pub fn with_redactor(self, redactor: DRedactor) -> Self {
+        self.state.update(|settings| settings.redactor=Some(Arc::new(redactor)));
+        self
+    }
+
+    /// Enable suppression of duplicates: consecutive identical records (level, target and message) are
+    /// written once, followed by a "previous message repeated N times" line when a different message arrives
+    /// or ['timeout'] has passed since the first repetition (zero to wait only for a different message).
+    /// 
+    /// With a timeout, a background thread writes the "repeated" line when it is due, even if no other
+    /// record is logged.
+    pub fn with_dedup(self, timeout: Duration) -> Self {
+        self.state.update(|settings| {
+            settings.dedup_enabled=true;
+            settings.dedup_timeout=timeout;
+        });
+        self
+    }
+
+    /// Limit and sample records using ['limiter'] rules.
+    pub fn with_rate_limiter(self, limiter: DRateLimiter) -> Self {
+        self.state.update(|settings| settings.rate_limiter=Some(Arc::new(limiter)));
+        self
+    }
+
+    /// Add ['sink'] as additional output (e.g. [`DSyslog`](crate::DSyslog)).
+    pub fn with_sink<S: DSink + 'static>(self, sink: S) -> Self {
+        self.state.update(|settings| settings.sinks.push(Arc::new(sink)));
+        self
+    }
+
+    /// Enable flight recorder: the last ['size'] records filtered out by level (e.g. trace and debug when
+    /// level is Info) are kept in memory and written when an Error record or a panic happens, to show what
+    /// led to the failure.
+    /// 
+    /// Dumped records go through the same outputs of the other records: console (if enabled), file or
+    /// routes, and additional outputs.
+    /// 
+    /// Panics are recorded only when used with std::log macros (see ['init_logger()']), because
+    /// the panic hook is installed (see ['with_panic_hook()']).
+    pub fn with_flight_recorder(self, size: usize) -> Self {
+        self.state.update(|settings| settings.flight_recorder_size=size);
+        self
+    }
+
+    /// Log panics: ['init_logger()'] installs a panic hook that writes the panic message, location and
+    /// thread name as an Error record, flushes all outputs and then runs the default hook.
+    /// 
+    /// If ['backtrace'] is true, a backtrace is added to the record.
+    pub fn with_panic_hook(self, backtrace: bool) -> Self {
+        self.state.update(|settings| {
+            settings.panic_hook_enabled=true;
+            settings.panic_backtrace_enabled=backtrace;
+        });
         self
     }
 
     /// Disable logging on stdout.
     pub fn without_console(mut self) -> Self {
-        self.log_on_stdout=false;
+        self.enable_console(false);
         self
     }
 
@@ -192,7 +670,15 @@ impl DLog {
     /// 
     /// Any use of ['debug!()'] will do nothing without  calling this function.
     pub fn init_logger(self) -> Result<(),SetLoggerError> {
-        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(LevelFilter::Trace))
+        let settings=self.state.settings();
+        // Flight recorder needs panics logged to be dumped
+        let panic_hook=settings.panic_hook_enabled || settings.flight_recorder_size > 0;
+        let backtrace=settings.panic_backtrace_enabled;
+        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(LevelFilter::Trace))?;
+        if panic_hook {
+            panic_hook::install(backtrace);
+        }
+        Ok(())
     }
 
 // **********************************************************
@@ -201,123 +687,163 @@ impl DLog {
 
     /// Enable/disable print in console(stdout).
     pub fn enable_console(&mut self, enabled: bool) {
-        self.log_on_stdout=enabled;
+        self.state.update(|settings| settings.log_on_stdout=enabled);
     }
     
     /// Enable/disable write in file.
     /// Works only if ['widh_file()'] function has previously called to set the filename.
     pub fn enable_file(&mut self, enabled: bool) {
-        self.log_on_file=enabled;
+        self.state.update(|settings| settings.log_on_file=enabled);
     }
 
     /// Enable/disable colors in console.
     pub fn enabled_colors(&mut self, enabled: bool) {
-        self.show_color_enabled=enabled;
+        self.state.update(|settings| settings.show_color_enabled=enabled);
     }
 
     /// Enable/disable showing timestamp in log string.
     pub fn enable_timestamp_print(&mut self, enabled: bool) {
-        self.show_timestamp_enabled=enabled;
+        self.state.update(|settings| settings.show_timestamp_enabled=enabled);
     }
 
     /// Enable/disable showing level in log string.
     pub fn enable_level_print(&mut self, enabled: bool) {
-        self.show_level_enabled=enabled;
+        self.state.update(|settings| settings.show_level_enabled=enabled);
+    }
+
+    /// Enable/disable showing target in log string (after level), for records that have one.
+    pub fn enable_target_print(&mut self, enabled: bool) {
+        self.state.update(|settings| settings.show_target_enabled=enabled);
+    }
+
+    /// Enable/disable brackets around level label.
+    pub fn enable_level_brackets(&mut self, enabled: bool) {
+        self.state.update(|settings| settings.level_brackets_enabled=enabled);
+    }
+
+    /// Write records kept by flight recorder through the outputs (console, files and additional outputs),
+    /// then clear it.
+    pub fn dump_flight_recorder(&self) {
+        self.state.dump_flight_recorder(&self.state.settings());
     }
 
     /// Log the ['msg'] string on ['Level::Error'].
     /// -Print on console if ['log_on_stdout'] is enabled.
     /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    #[track_caller]
     pub fn e(&self, msg: &str) {
-        self.write(Level::Error, msg);
+        self.write(Level::Error, msg, Location::caller());
     }
 
     /// Log the ['msg'] string on ['Level::Warn'].
     /// -Print on console if ['log_on_stdout'] is enabled.
     /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    #[track_caller]
     pub fn w(&self, msg: &str) {
-        self.write(Level::Warn, msg);
+        self.write(Level::Warn, msg, Location::caller());
     }
 
     /// Log the ['msg'] string on ['Level::Info'].
     /// -Print on console if ['log_on_stdout'] is enabled.
     /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    #[track_caller]
     pub fn i(&self, msg: &str) {
-        self.write(Level::Info, msg);
+        self.write(Level::Info, msg, Location::caller());
     }
 
     /// Log the ['msg'] string on ['Level::Debug'].
     /// -Print on console if ['log_on_stdout'] is enabled.
     /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    #[track_caller]
     pub fn d(&self, msg: &str) {
-        self.write(Level::Debug, msg);
+        self.write(Level::Debug, msg, Location::caller());
     }
 
     /// Log the ['msg'] string on ['Level::Trace'].
     /// -Print on console if ['log_on_stdout'] is enabled.
     /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    #[track_caller]
     pub fn t(&self, msg: &str) {
-        self.write(Level::Trace, msg);
+        self.write(Level::Trace, msg, Location::caller());
     }
 
-// ******************* api for internal use *******************
-    /// Log the ['msg'] string on ['level'] level.
-    /// -Print on console if ['log_on_stdout'] is enabled.
-    /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
-    fn write(&self, level: Level, msg: &str) {
-        // Now string
-        let timestamp_str = Utc::now().format(&self.timestamp_format).to_string() + &self.separator;
-        // Level string
-        let level_str=self.level_to_str(level).to_string() + &self.separator;
+    /// ['return'] true if a record of ['level'] and ['target'] passes level and target filters.
+    /// 
+    /// Records without target (direct api not scoped with ['target()']) always pass the target filter.
+    pub fn is_enabled(&self, level: Level, target: &str) -> bool {
+        self.state.settings().is_enabled(level, target)
+    }
 
-        if self.log_on_stdout {
-            // Print on stdout (use color if set)
-            write!(stdout(),"{}\n",
-                if self.show_color_enabled {self.level_to_color(level).to_string()} else {String::new()} +
-                if self.show_timestamp_enabled {&timestamp_str} else {""} +
-                if self.show_level_enabled {&level_str} else {""} +
-                msg +
-                if self.show_color_enabled {&COLOR_DEFAULT} else {""}
-            ).ok();
-        }
+    /// Log ['args'] on ['level'], only if ['level'] is enabled: arguments are not formatted otherwise.
+    /// 
+    /// Usually called by [`dlog_e!`](crate::dlog_e) and the other `dlog_*!` macros.
+    #[track_caller]
+    pub fn log_fmt(&self, level: Level, args: fmt::Arguments) {
+        self.state.start_ticker();
+        self.state.log_args(level, "", &[], args, Location::caller());
+    }
 
-        if self.log_on_file {
-            // Write in file
-            self.write_file(
-                &(
-                    if self.show_timestamp_enabled {timestamp_str} else {String::new()} +
-                    if self.show_level_enabled {&level_str} else {""} +
-                    msg
-                )
-            ).ok();
+    /// ['return'] a child logger that logs with ['target'], so that target filter is applied.
+    /// 
+    /// ### Example
+    /// ```rust
+    /// use durylog::DLog;
+    /// 
+    /// let durylog=DLog::new().with_target_filter("net");
+    /// durylog.target("net").d("Connected"); // logged
+    /// durylog.target("db").d("Query"); // filtered out
+    /// ```
+    pub fn target(&self, target: &str) -> DScope {
+        DScope {
+            state: Arc::clone(&self.state),
+            target: target.to_string(),
+            fields: Vec::new(),
         }
     }
 
-    /// ['return'] a string associated to ['level'].
-    fn level_to_str(&self, level: Level) -> &'static str {
-        match level {
-            Level::Error    => STR_ERROR,
-            Level::Warn     => STR_WARN,
-            Level::Info     => STR_INFO,
-            Level::Debug    => STR_DEBUG,
-            Level::Trace    => STR_TRACE,
-        }
+    /// ['return'] a child logger named ['name']: it shares outputs and settings of this logger and logs
+    /// with ['name'] as target. Use [`DScope::with_context()`] to add fields to every record.
+    /// 
+    /// Settings changed on this logger later apply also to its child loggers.
+    /// 
+    /// ### Example
+    /// ```rust
+    /// use durylog::DLog;
+    /// 
+    /// let mut durylog=DLog::new();
+    /// durylog.enable_target_print(true);
+    /// 
+    /// let db=durylog.child("db").with_context("request_id", "42");
+    /// db.i("Query done"); // 2023/01/02 18.01.27 : INFO   : db : Query done request_id=42
+    /// db.child("pool").d("Connection released"); // target is "db::pool", request_id is inherited
+    /// ```
+    pub fn child(&self, name: &str) -> DScope {
+        self.target(name)
     }
 
-    /// ['return'] a color pattern associated to ['level'].
-    fn level_to_color(&self, level: Level) -> &'static str {
-        match level {
-            Level::Error    => COLOR_RED,
-            Level::Warn     => COLOR_YELLOY,
-            Level::Info     => COLOR_DEFAULT,
-            Level::Debug    => COLOR_CYAN,
-            Level::Trace    => COLOR_LIME,
-        }
+    /// ['return'] a child logger that adds field ['key']=['value'] to every record.
+    pub fn with_context<V: fmt::Display>(&self, key: &str, value: V) -> DScope {
+        self.target("").with_context(key, value)
+    }
+
+    /// ['return'] a guard that logs "start ['label']" on Info level now and "end ['label'] took ...ms" when
+    /// dropped. See [`DTimer`].
+    #[track_caller]
+    pub fn timed(&self, label: &str) -> DTimer {
+        self.timed_at(Level::Info, label)
+    }
+
+    /// Like [`DLog::timed()`], logging on ['level'].
+    #[track_caller]
+    pub fn timed_at(&self, level: Level, label: &str) -> DTimer {
+        DTimer::new(self.target(""), level, label)
     }
 
     /// ['return'] info about durylog crate setting.
     /// ### Example
     /// ```rust
+    /// use durylog::DLog;
+    /// 
     /// let durylog=DLog::new();
     /// println!("{}", durylog.get_status());
     /// ```
@@ -327,22 +853,41 @@ impl DLog {
     /// Show Colors       =  false
     /// Show Level        =  true
     /// Show Timestamp    =  true
+    /// Show Target       =  false
     /// Timestamp Format  =  %Y/%m/%d %H.%M.%S
     /// Tags separator    =  ' : '
+    /// Level labels      =  ERROR WARN INFO DEBUG TRACE
+    /// Level align       =  Left
+    /// Level brackets    =  false
+    /// Format            =  Text
+    /// Multi-line        =  Keep
+    /// Console sanitize  =  Off
+    /// File sanitize     =  Off
+    /// Redaction         =  false
+    /// Dedup             =  false
+    /// Rate limit        =  false
+    /// Sinks             =  0
+    /// Flight recorder   =  disabled
+    /// Panic hook        =  false
     /// Level             =  TRACE
     /// Log on stdout     =  true
     /// Log on file       =  false
     /// Max file size     =  no limit
     /// Max files count   =  no limit
+    /// File routes       =  0
+    /// Create dirs       =  false
+    /// File mode         =  default
+    /// File lock         =  false
     /// --------------------------------------
     /// ```
     pub fn get_status(&self) -> String {
-        let max_file_size=self.max_file_size.to_string();
-        let max_files_count=self.max_files_count.to_string();
+        let settings=self.state.settings();
+        let max_file_size=settings.max_file_size.to_string();
+        let max_files_count=settings.max_files_count.to_string();
 
         let mut filename_str=String::new();
-        if self.log_on_file {
-            let binding = self.filename.canonicalize().ok().unwrap_or_default();
+        if settings.log_on_file {
+            let binding = settings.file.as_ref().and_then(|f| f.path().canonicalize().ok()).unwrap_or_default();
             filename_str.push_str("Current filename  =  ");
             filename_str.push_str(binding.to_str().unwrap_or_default());
             filename_str.push('\n');
@@ -350,136 +895,951 @@ impl DLog {
 
         let status_info=String::new() +
             "----------- durylog current settings -----------" + "\n" +
-            "Show Colors       =  " + &self.show_color_enabled.to_string() + "\n" +
-            "Show Level        =  " + &self.show_level_enabled.to_string() + "\n" +
-            "Show Timestamp    =  " + &self.show_timestamp_enabled.to_string() + "\n" +
-            "Timestamp Format  =  " + &self.timestamp_format.to_string() + "\n" +
-            "Tags separator    =  '" + &self.separator + "'\n" +
-            "Level             =  " + &self.level.to_string() + "\n" +
-            "Log on stdout     =  " + &self.log_on_stdout.to_string() + "\n" +
-            "Log on file       =  " + &self.log_on_file.to_string() + "\n" +
-            if self.log_on_file {&filename_str} else {""} +
-            "Max file size     =  " + if self.max_file_size > 0 {&max_file_size} else {"no limit"} + "\n" +
-            "Max files count   =  " + if self.max_files_count > 0 {&max_files_count} else {"no limit"} + "\n" +
+            "Show Colors       =  " + &settings.show_color_enabled.to_string() + "\n" +
+            "Show Level        =  " + &settings.show_level_enabled.to_string() + "\n" +
+            "Show Timestamp    =  " + &settings.show_timestamp_enabled.to_string() + "\n" +
+            "Show Target       =  " + &settings.show_target_enabled.to_string() + "\n" +
+            "Timestamp Format  =  " + &settings.timestamp_format.to_string() + "\n" +
+            "Tags separator    =  '" + &settings.separator + "'\n" +
+            "Level labels      =  " + &settings.level_labels.join(" ") + "\n" +
+            "Level align       =  " + &format!("{:?}", settings.level_align) + "\n" +
+            "Level brackets    =  " + &settings.level_brackets_enabled.to_string() + "\n" +
+            "Format            =  " + &format!("{:?}", settings.format) + "\n" +
+            "Multi-line        =  " + &format!("{:?}", settings.multiline) + "\n" +
+            "Console sanitize  =  " + &format!("{:?}", settings.console_sanitize) + "\n" +
+            "File sanitize     =  " + &format!("{:?}", settings.file_sanitize) + "\n" +
+            "Redaction         =  " + &settings.redactor.is_some().to_string() + "\n" +
+            "Dedup             =  " + &settings.dedup_enabled.to_string() + "\n" +
+            "Rate limit        =  " + &settings.rate_limiter.is_some().to_string() + "\n" +
+            "Sinks             =  " + &settings.sinks.len().to_string() + "\n" +
+            "Flight recorder   =  " + &if settings.flight_recorder_size > 0 {settings.flight_recorder_size.to_string()} else {String::from("disabled")} + "\n" +
+            "Panic hook        =  " + &settings.panic_hook_enabled.to_string() + "\n" +
+            "Level             =  " + &settings.level.to_string() + "\n" +
+            "Log on stdout     =  " + &settings.log_on_stdout.to_string() + "\n" +
+            "Log on file       =  " + &settings.log_on_file.to_string() + "\n" +
+            if settings.log_on_file {&filename_str} else {""} +
+            "Max file size     =  " + if settings.max_file_size > 0 {&max_file_size} else {"no limit"} + "\n" +
+            "Max files count   =  " + if settings.max_files_count > 0 {&max_files_count} else {"no limit"} + "\n" +
+            "File routes       =  " + &settings.routes.len().to_string() + "\n" +
+            "Create dirs       =  " + &settings.file_options.create_dirs.to_string() + "\n" +
+            "File mode         =  " + &settings.file_options.mode.map(|m| format!("{:o}", m)).unwrap_or(String::from("default")) + "\n" +
+            "File lock         =  " + &settings.file_options.lock.to_string() + "\n" +
             "---------------------------------------------";
 
         status_info
     }
-// *******************************************************************
 
-// *************************** File handle ***************************
-    /// Open ['filename'] for with options enabled: read, write, create, append.
-    fn open_file(&self, filename: &str) -> io::Result<File> {
-        let f = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(filename)?;
-    
-        Ok(f)
+    /// Log the ['msg'] string on ['level'] level, called at ['location'].
+    /// -Print on console if ['log_on_stdout'] is enabled.
+    /// -Print in file if ['log_on_file'] is enabled and file is initialized with ['with_file()'].
+    fn write(&self, level: Level, msg: &str, location: &Location) {
+        self.state.start_ticker();
+        self.state.write(level, msg, location);
     }
+// **********************************************************
+}
 
-    /// Write string ['msg'] into file.
-    /// N.B. If ['file'] is not opened, nothing happens.
-    fn write_file(&self, msg: &str) -> Result<usize,DLogError> {
-        if let Some(file) = &self.file {
-            let mut f=file;
-            let s=format!("{}\n",msg);
-            match f.write(s.as_bytes()) {
-                Ok(b_written) => {
-                    self.check_storage()?;
-                    return Ok(b_written);
+impl DLogState {
+// ******************* api for internal use *******************
+    /// ['return'] current settings.
+    fn settings(&self) -> Arc<DSettings> {
+        Arc::clone(&self.settings.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Change settings with ['change']: records already being written keep the previous ones.
+    fn update(&self, change: impl FnOnce(&mut DSettings)) {
+        let mut settings=self.settings.write().unwrap_or_else(|e| e.into_inner());
+        change(Arc::make_mut(&mut settings));
+    }
+
+    /// Log the ['msg'] string on ['level'] level, called at ['location'].
+    fn write(&self, level: Level, msg: &str, location: &Location) {
+        let settings=self.settings();
+        if self.is_wanted(&settings, level, "", Some(location.file()), Some(location.line())) {
+            let mut record=DRecord::new(level, msg);
+            record.file=Some(location.file().to_string());
+            record.line=Some(location.line());
+            self.log_record(&settings, record);
+        }
+    }
+
+    /// Start, once, the thread that writes time based lines when they are due, so that they don't wait for
+    /// the next record. It ends when all handles of ['self'] are dropped.
+    fn start_ticker(self: &Arc<Self>) {
+        if self.ticker.get().is_some() || self.settings().tick_interval().is_none() {
+            return;
+        }
+        self.ticker.get_or_init(|| {
+            let state=Arc::downgrade(self);
+            thread::Builder::new().name(String::from("dlog-ticker")).spawn(move || loop {
+                // Settings may change meanwhile
+                let interval=match state.upgrade() {
+                    Some(state) => state.settings().tick_interval().unwrap_or(MAX_TICK_INTERVAL),
+                    None => break,
+                };
+                thread::sleep(interval);
+                let Some(state)=state.upgrade() else { break };
+                state.tick();
+            }).ok();
+        });
+    }
+
+    /// Write time based lines that are due: "repeated" line when dedup timeout has passed and rate limiter
+    /// summary when its interval has passed.
+    fn tick(&self) {
+        let settings=self.settings();
+        let repeated=self.dedup_state.lock().unwrap_or_else(|e| e.into_inner()).as_mut().and_then(|last| {
+            (last.count > 0 && clock::now().duration_since(last.since) >= settings.dedup_timeout).then(|| {
+                let count=last.count;
+                last.count=0;
+                last.since=clock::now();
+                (last.level, last.target.clone(), count)
+            })
+        });
+        if let Some((level, target, count)) = repeated {
+            settings.write_repeated(level, target, count);
+        }
+
+        if let Some(limiter) = &settings.rate_limiter {
+            settings.write_summary(limiter.take_summary(false));
+        }
+    }
+
+    /// Format ['args'] and log them with ['target'] and ['fields'], only if they will be written or kept by flight recorder.
+    /// ['location'] is where they have been logged.
+    fn log_args(&self, level: Level, target: &str, fields: &[(String, String)], args: fmt::Arguments, location: &Location) {
+        let settings=self.settings();
+        if self.is_wanted(&settings, level, target, Some(location.file()), Some(location.line())) {
+            let mut record=match args.as_str() {
+                Some(msg) => DRecord::new(level, msg),
+                None => DRecord::new(level, &args.to_string()),
+            };
+            record.target=target.to_string();
+            record.file=Some(location.file().to_string());
+            record.line=Some(location.line());
+            record.fields.extend_from_slice(fields);
+            self.log_record(&settings, record);
+        }
+    }
+
+    /// ['return'] true if a record of ['level'] and ['target'], logged at ['file']:['line'], has to be formatted and
+    /// logged: it passes filters and rate limits, or it is kept by flight recorder.
+    fn is_wanted(&self, settings: &DSettings, level: Level, target: &str, file: Option<&str>, line: Option<u32>) -> bool {
+        if settings.is_enabled(level, target) {
+            settings.rate_limiter.as_ref().is_none_or(|limiter| limiter.check(level, target, file, line))
+        } else {
+            settings.is_flight_recorded(level, target)
+        }
+    }
+
+    /// Filter ['record'] (already allowed by ['is_wanted()']): write it if enabled, otherwise keep it in flight
+    /// recorder (if enabled).
+    /// 
+    /// This is the only filtering path, for both direct api and std::log macros.
+    fn log_record(&self, settings: &DSettings, mut record: DRecord) {
+        let context=context::fields();
+        if !context.is_empty() {
+            record.fields.splice(0..0, context);
+        }
+
+        if settings.is_enabled(record.level, &record.target) {
+            self.write_record(settings, &record);
+        } else if settings.is_flight_recorded(record.level, &record.target) {
+            self.record_flight(settings, record);
+        }
+    }
+
+    /// Write ['record'] on console and/or file.
+    fn write_record(&self, settings: &DSettings, record: &DRecord) {
+        if settings.flight_recorder_size > 0 && record.level == Level::Error {
+            self.dump_flight_recorder(settings);
+        }
+        if settings.dedup_enabled && !self.dedup_check(settings, record) {
+            return;
+        }
+        settings.write_filtered(record);
+    }
+
+    /// ['return'] true if ['record'] has to be written, false if it is a suppressed duplicate.
+    /// Writes the "repeated" line when needed.
+    fn dedup_check(&self, settings: &DSettings, record: &DRecord) -> bool {
+        let mut repeated=None;
+        let write_it={
+            let mut state=self.dedup_state.lock().unwrap_or_else(|e| e.into_inner());
+            match state.as_mut() {
+                Some(last) if last.level == record.level && last.target == record.target && last.message == record.message => {
+                    last.count+=1;
+                    if !settings.dedup_timeout.is_zero() && clock::now().duration_since(last.since) >= settings.dedup_timeout {
+                        repeated=Some((last.level, last.target.clone(), last.count));
+                        last.count=0;
+                        last.since=clock::now();
+                    }
+                    false
                 },
-                Err(err) => {
-                    return Err(DLogError::Err(err));
+                _ => {
+                    if let Some(last) = state.as_ref() {
+                        if last.count > 0 {
+                            repeated=Some((last.level, last.target.clone(), last.count));
+                        }
+                    }
+                    *state=Some(DDedupState {
+                        level: record.level,
+                        target: record.target.clone(),
+                        message: record.message.clone(),
+                        count: 0,
+                        since: clock::now(),
+                    });
+                    true
                 },
             }
+        };
+
+        if let Some((level, target, count)) = repeated {
+            settings.write_repeated(level, target, count);
         }
-        Ok(0)
+        write_it
     }
 
-    /// Make a files rotation/delete due to the settings.
-    fn check_storage(&self) -> Result<(),DLogError> {
-        if let Some(file) = &self.file {
-            // Check for current file size
-            let f=file;
-            match f.metadata() {
-                Ok(metadata) => {
-                    if self.max_file_size > 0 && metadata.len() > self.max_file_size {
-                        // exceed max size
-                        self.write(Level::Trace, &format!("Current log size {} exceed {}, need to rotate",metadata.len(),self.max_file_size));
-                        self.rotate_files()?;
-                    };
-                    return Ok(());
-                }
-                Err(err) => return Err(DLogError::Err(err)),
+    /// Write records kept by flight recorder through the outputs, between a header and a footer line, then
+    /// clear it.
+    fn dump_flight_recorder(&self, settings: &DSettings) {
+        let records: Vec<DRecord>=self.flight_recorder.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect();
+        if records.is_empty() {
+            return;
+        }
+
+        settings.write_filtered(&DRecord::new(Level::Info, &format!("---- flight recorder: last {} records ----", records.len())));
+        for record in &records {
+            settings.write_filtered(record);
+        }
+        settings.write_filtered(&DRecord::new(Level::Info, "---- end of flight recorder ----"));
+    }
+
+    /// Keep ['record'] in flight recorder.
+    fn record_flight(&self, settings: &DSettings, record: DRecord) {
+        let mut ring=self.flight_recorder.lock().unwrap_or_else(|e| e.into_inner());
+        while ring.len() >= settings.flight_recorder_size {
+            ring.pop_front();
+        }
+        ring.push_back(record);
+    }
+
+    /// Write pending lines and flush all outputs.
+    fn flush(&self) {
+        let settings=self.settings();
+        // Write pending "repeated" line
+        let pending=self.dedup_state.lock().unwrap_or_else(|e| e.into_inner()).as_mut().and_then(|last| {
+            let count=last.count;
+            last.count=0;
+            (count > 0).then(|| (last.level, last.target.clone(), count))
+        });
+        if let Some((level, target, count)) = pending {
+            settings.write_repeated(level, target, count);
+        }
+
+        // Write pending rate limiter summary
+        if let Some(limiter) = &settings.rate_limiter {
+            settings.write_summary(limiter.take_summary(true));
+        }
+
+        if let Some(file) = &settings.file {
+            file.flush();
+        }
+        for (_, file) in &settings.routes {
+            file.flush();
+        }
+
+        for sink in &settings.sinks {
+            sink.flush();
+        }
+    }
+}
+
+impl DSettings {
+    /// ['return'] how often time based lines are checked, None if there are none: "repeated" lines of
+    /// duplicates suppression with a timeout and rate limiter summaries.
+    fn tick_interval(&self) -> Option<Duration> {
+        let dedup=if self.dedup_enabled { self.dedup_timeout } else { Duration::ZERO };
+        let summary=self.rate_limiter.as_ref().map(|limiter| limiter.summary_interval()).unwrap_or_default();
+        [dedup, summary].into_iter()
+            .filter(|interval| !interval.is_zero())
+            .min()
+            .map(|interval| interval.min(MAX_TICK_INTERVAL))
+    }
+
+    /// ['return'] true if a record of ['level'] and ['target'] passes level and target filters.
+    /// 
+    /// Records without target (direct api not scoped with ['target()']) always pass the target filter.
+    fn is_enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level && self.is_target_enabled(target)
+    }
+
+    /// ['return'] true if a record with ['target'] passes target filter.
+    fn is_target_enabled(&self, target: &str) -> bool {
+        match &self.target {
+            Some(t) => target.is_empty() || target.starts_with(t.as_str()),
+            None => true,
+        }
+    }
+
+    /// ['return'] true if a record of ['level'] and ['target'] filtered out by level is kept by flight recorder.
+    /// Records filtered out by target are never kept.
+    fn is_flight_recorded(&self, level: Level, target: &str) -> bool {
+        self.flight_recorder_size > 0 && level > self.level && self.is_target_enabled(target)
+    }
+
+    /// Write rate limiter summary ['lines'].
+    fn write_summary(&self, lines: Vec<String>) {
+        for line in lines {
+            self.write_filtered(&DRecord::new(Level::Warn, &line));
+        }
+    }
+
+    /// Write the "previous message repeated ['count'] times" line.
+    fn write_repeated(&self, level: Level, target: String, count: u64) {
+        let mut record=DRecord::new(level, &format!("previous message repeated {} times", count));
+        record.target=target;
+        self.write_filtered(&record);
+    }
+
+    /// Write ['record'] on console and/or file, after redaction.
+    fn write_filtered(&self, record: &DRecord) {
+        match &self.redactor {
+            Some(redactor) => {
+                let mut record=record.clone();
+                redactor.redact(&mut record);
+                self.write_redacted(&record);
+            },
+            None => self.write_redacted(record),
+        }
+    }
+
+    /// Write ['record'] (already redacted) on console and/or file.
+    fn write_redacted(&self, record: &DRecord) {
+        if self.log_on_stdout {
+            // Print on stdout (use color if set, text format only)
+            let line=self.format_line(record, self.console_sanitize);
+            let color=self.show_color_enabled && self.format == DFormat::Text;
+            writeln!(stdout(),"{}",
+                if color {self.level_to_color(record.level).to_string()} else {String::new()} +
+                &line +
+                if color {COLOR_DEFAULT} else {""}
+            ).ok();
+        }
+
+        if self.log_on_file || !self.routes.is_empty() || !self.sinks.is_empty() {
+            // Files and additional outputs get the same sanitized record
+            match self.file_sanitize {
+                DSanitize::Off => self.write_outputs(record),
+                sanitize => self.write_outputs(&sanitize_record(record, sanitize)),
             }
         }
-        Ok(())
     }
 
-    fn rotate_files(&self) -> Result<(),DLogError>{
-        // Get list of log files
-        let mut files_list=self.get_files()?;
-        files_list.sort();
+    /// Write ['record'] (already redacted and sanitized) in files and on additional outputs.
+    fn write_outputs(&self, record: &DRecord) {
+        let line=self.format_line(record, DSanitize::Off);
+        // Write in routed files, then in main file if not routed
+        let routed=!self.write_routes(record, &line);
+        let in_file=!routed && self.log_on_file;
+        if in_file {
+            self.write_file(&line).ok();
+        }
 
-        // Check for max files count
-        if self.max_files_count > 0 && files_list.len() >= self.max_files_count as usize {
-            // Delete all files that exceeds max files count
-            self.write(Level::Trace, "Files that needs to be deleted:");
-            files_list.into_iter().nth(self.max_files_count.saturating_sub(1) as usize).map(|path| {
-                self.write(Level::Trace, path.to_str().unwrap());
-                //fs::remove_file(path)
-            });
+        // Write on additional outputs, lines they cannot keep go in main file if not already there
+        for sink in &self.sinks {
+            if matches!(sink.write(record, &line), Err(DLogError::Overflow)) && !in_file {
+                self.write_file(&line).ok();
+            }
         }
+    }
 
-        // TODO: rename current file
-        // TODO: create and open new one
+    /// ['return'] ['record'] formatted due to ['format'] setting, sanitized due to ['sanitize'].
+    fn format_line(&self, record: &DRecord, sanitize: DSanitize) -> String {
+        match self.format {
+            DFormat::Text => self.format_lines(&self.format_prefix(record), &self.format_text(record, sanitize)),
+            DFormat::Json | DFormat::Logfmt => {
+                let record=sanitize_record(record, sanitize);
+                if self.format == DFormat::Json { record_json(&record) } else { record_logfmt(&record) }
+            },
+        }
+    }
 
-        Ok(())
+    /// ['return'] timestamp and level tags of ['record'], as enabled.
+    fn format_prefix(&self, record: &DRecord) -> String {
+        // Now string
+        let timestamp_str = record.timestamp.format(&self.timestamp_format).to_string() + &self.separator;
+        // Level string
+        let level_str=self.level_to_str(record.level) + &self.separator;
+
+        // Target string
+        let target_str=record.target.clone() + &self.separator;
+
+        String::new() +
+            if self.show_timestamp_enabled {&timestamp_str} else {""} +
+            if self.show_level_enabled {&level_str} else {""} +
+            if self.show_target_enabled && !record.target.is_empty() {&target_str} else {""}
     }
 
-    /// ['return'] a vector containing a list of all files in the ['self.filename'] directory path and with ['self.filename'] extension.
-    fn get_files(&self) -> Result<Vec<PathBuf>, DLogError> {
-        //let filename=Path::new(&self.filename);
-
-        match fs::read_dir(&self.filename.parent().unwrap()) {
-            Ok(r) => {
-                return Ok(
-                    r.into_iter()
-                    .filter(|r| r.is_ok()) // Get rid of Err variants for Result<DirEntry>
-                    .map(|r| {
-                        r.unwrap().path().canonicalize().unwrap()
-                    }) // This is safe, since we only have the Ok variants
-                    .filter(|r| r.is_file()) // only files
-                    .filter(|r|r.extension().unwrap().eq_ignore_ascii_case(&self.filename.extension().unwrap())) // only files with .log extension
-                    .collect())
+    /// ['return'] message of ['record'] followed by its fields as ' key=value', sanitized due to ['sanitize'].
+    fn format_text(&self, record: &DRecord, sanitize: DSanitize) -> String {
+        let mut text=sanitize_str(&record.message, sanitize);
+        for (key, value) in &record.fields {
+            text.push(' ');
+            text.push_str(&sanitize_str(key, sanitize));
+            text.push('=');
+            text.push_str(&sanitize_str(value, sanitize));
+        }
+        text
+    }
+
+    /// ['return'] ['prefix'] followed by ['msg'], with newlines handled due to ['multiline'] setting.
+    fn format_lines(&self, prefix: &str, msg: &str) -> String {
+        if !msg.contains(['\n', '\r']) {
+            return String::new() + prefix + msg;
+        }
+
+        match self.multiline {
+            DMultiline::Keep => String::new() + prefix + msg,
+            DMultiline::Repeat => {
+                msg.lines()
+                    .map(|l| String::new() + prefix + l)
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            },
+            DMultiline::Indent => {
+                let indent=" ".repeat(prefix.chars().count());
+                String::new() + prefix + &msg.lines().collect::<Vec<&str>>().join(&(String::from("\n") + &indent))
             },
-            Err(err) => return Err(DLogError::Err(err)),
+            DMultiline::Escape => {
+                String::new() + prefix + &msg.replace('\r', "\\r").replace('\n', "\\n")
+            },
+        }
+    }
+
+    /// ['return'] the level tag associated to ['level'], with brackets and alignment applied.
+    fn level_to_str(&self, level: Level) -> String {
+        let label=&self.level_labels[level as usize - 1];
+        let (label, width)=if self.level_brackets_enabled {
+            (format!("[{}]", label), self.level_width + 2)
+        } else {
+            (label.to_string(), self.level_width)
+        };
+
+        match self.level_align {
+            DLevelAlign::Left   => format!("{:<width$}", label),
+            DLevelAlign::Right  => format!("{:>w$} ", label, w=width - 1),
+            DLevelAlign::None   => label,
         }
     }
+
+    /// ['return'] a color pattern associated to ['level'].
+    fn level_to_color(&self, level: Level) -> &'static str {
+        match level {
+            Level::Error    => COLOR_RED,
+            Level::Warn     => COLOR_YELLOY,
+            Level::Info     => COLOR_DEFAULT,
+            Level::Debug    => COLOR_CYAN,
+            Level::Trace    => COLOR_LIME,
+        }
+    }
+
+// *************************** File handle ***************************
+    /// Apply rotation settings to main file.
+    fn apply_rotation(&self) {
+        if let Some(file) = &self.file {
+            file.set_rotation(self.max_file_size, self.max_files_count);
+        }
+    }
+
+    /// Apply creation options to files already opened (main file and routes).
+    fn apply_file_options(&self) {
+        for file in self.file.iter().chain(self.routes.iter().map(|(_, file)| file)) {
+            // Errors are ignored, as for writes
+            file.set_options(self.file_options).ok();
+        }
+    }
+
+    /// Open ['filename'] (a template, see ['with_file()']) with rotation and creation options set.
+    fn open_file(&self, filename: &str) -> Result<DFile, DLogError> {
+        let file=DFile::open(filename, self.file_options)?;
+        file.set_rotation(self.max_file_size, self.max_files_count);
+        Ok(file)
+    }
+
+    /// Write string ['msg'] into main file.
+    /// N.B. If ['file'] is not opened, nothing happens.
+    fn write_file(&self, msg: &str) -> Result<usize,DLogError> {
+        if let Some(file) = &self.file {
+            return file.write_file(msg);
+        }
+        Ok(0)
+    }
+
+    /// Write ['line'] of ['record'] into matching routes files.
+    /// ['return'] true if record must be written also in main file.
+    fn write_routes(&self, record: &DRecord, line: &str) -> bool {
+        for (route, file) in &self.routes {
+            if route.matches(record.level, &record.target) {
+                file.write_file(line).ok();
+                if !route.is_passthrough() {
+                    return false;
+                }
+            }
+        }
+        true
+    }
 // ************************* end File handle *************************
 }
 
+/// A child logger of a [`DLog`] that logs with a target and context fields (see [`DLog::target()`],
+/// [`DLog::child()`], [`DLog::with_context()`]).
+/// 
+/// It shares outputs and settings with its [`DLog`], so it is cheap to create. It is an owned handle: it can
+/// be stored, sent to other threads and used after [`DLog::init_logger()`].
+#[derive(Debug, Clone)]
+pub struct DScope {
+    /// Settings and outputs of parent logger
+    state: Arc<DLogState>,
+    /// Target of records
+    target: String,
+    /// Fields added to every record
+    fields: Vec<(String, String)>,
+}
+
+impl DScope {
+    /// ['return'] a child logger: target is this target followed by '::' and ['name'], fields are inherited.
+    pub fn child(&self, name: &str) -> DScope {
+        DScope {
+            state: Arc::clone(&self.state),
+            target: if self.target.is_empty() { name.to_string() } else { format!("{}::{}", self.target, name) },
+            fields: self.fields.clone(),
+        }
+    }
+
+    /// Add field ['key']=['value'] to every record.
+    pub fn with_context<V: fmt::Display>(mut self, key: &str, value: V) -> Self {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// ['return'] true if a record of ['level'] passes level and target filters.
+    pub fn is_enabled(&self, level: Level) -> bool {
+        self.state.settings().is_enabled(level, &self.target)
+    }
+
+    /// Log ['args'] on ['level'], only if enabled (see [`DLog::log_fmt()`]).
+    #[track_caller]
+    pub fn log_fmt(&self, level: Level, args: fmt::Arguments) {
+        self.log_at(level, args, Location::caller());
+    }
+
+    /// Log ['args'] on ['level'] as called at ['location'], only if enabled.
+    pub(crate) fn log_at(&self, level: Level, args: fmt::Arguments, location: &Location) {
+        self.state.start_ticker();
+        self.state.log_args(level, &self.target, &self.fields, args, location);
+    }
+
+    /// ['return'] a timer that logs with this target and fields (see [`DLog::timed()`]).
+    #[track_caller]
+    pub fn timed(&self, label: &str) -> DTimer {
+        self.timed_at(Level::Info, label)
+    }
+
+    /// Like [`DScope::timed()`], logging on ['level'].
+    #[track_caller]
+    pub fn timed_at(&self, level: Level, label: &str) -> DTimer {
+        DTimer::new(self.clone(), level, label)
+    }
+
+    /// Log the ['msg'] string on ['Level::Error'].
+    #[track_caller]
+    pub fn e(&self, msg: &str) {
+        self.log_fmt(Level::Error, format_args!("{}", msg));
+    }
+
+    /// Log the ['msg'] string on ['Level::Warn'].
+    #[track_caller]
+    pub fn w(&self, msg: &str) {
+        self.log_fmt(Level::Warn, format_args!("{}", msg));
+    }
+
+    /// Log the ['msg'] string on ['Level::Info'].
+    #[track_caller]
+    pub fn i(&self, msg: &str) {
+        self.log_fmt(Level::Info, format_args!("{}", msg));
+    }
+
+    /// Log the ['msg'] string on ['Level::Debug'].
+    #[track_caller]
+    pub fn d(&self, msg: &str) {
+        self.log_fmt(Level::Debug, format_args!("{}", msg));
+    }
+
+    /// Log the ['msg'] string on ['Level::Trace'].
+    #[track_caller]
+    pub fn t(&self, msg: &str) {
+        self.log_fmt(Level::Trace, format_args!("{}", msg));
+    }
+}
+
 impl Log for DLog {
+    /// ['return'] true if a record with ['metadata'] is written, or kept by flight recorder.
     fn enabled(&self, metadata: &Metadata) -> bool {
-        if let Some(level) = self.level.to_level() {
-            if level >= metadata.level() {
-                return match &self.target {
-                    Some(t) => metadata.target().starts_with(t),
-                    None => true,
-                };
+        let settings=self.state.settings();
+        settings.is_enabled(metadata.level(), metadata.target()) || settings.is_flight_recorded(metadata.level(), metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        self.state.start_ticker();
+        let settings=self.state.settings();
+        if self.state.is_wanted(&settings, record.level(), record.target(), record.file(), record.line()) {
+            self.state.log_record(&settings, DRecord::from_log(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.state.flush();
+    }
+}
+
+impl Drop for DLogState {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// ['return'] a copy of ['record'] with message and fields sanitized due to ['sanitize'].
+fn sanitize_record(record: &DRecord, sanitize: DSanitize) -> DRecord {
+    let mut record=record.clone();
+    record.message=sanitize_str(&record.message, sanitize);
+    for (key, value) in record.fields.iter_mut() {
+        *key=sanitize_str(key, sanitize);
+        *value=sanitize_str(value, sanitize);
+    }
+    record
+}
+
+/// ['return'] ['s'] with control characters and ANSI escape sequences handled due to ['sanitize'].
+fn sanitize_str(s: &str, sanitize: DSanitize) -> String {
+    match sanitize {
+        DSanitize::Off => s.to_string(),
+        DSanitize::Escape => {
+            let mut out=String::with_capacity(s.len());
+            for c in s.chars() {
+                match c {
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+                    c => out.push(c),
+                }
             }
+            out
+        },
+        DSanitize::Strip => {
+            let mut out=String::with_capacity(s.len());
+            let mut chars=s.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\x1B' => {
+                        match chars.peek() {
+                            // CSI: ESC [ params final-byte
+                            Some('[') => {
+                                chars.next();
+                                for c in chars.by_ref() {
+                                    if ('\x40'..='\x7E').contains(&c) {
+                                        break;
+                                    }
+                                }
+                            },
+                            // OSC: ESC ] ... BEL or ESC \
+                            Some(']') => {
+                                chars.next();
+                                while let Some(c) = chars.next() {
+                                    if c == '\x07' || (c == '\x1B' && chars.next_if_eq(&'\\').is_some()) {
+                                        break;
+                                    }
+                                }
+                            },
+                            // Two chars sequence
+                            Some(_) => {
+                                chars.next();
+                            },
+                            None => {},
+                        }
+                    },
+                    '\n' | '\r' | '\t' => out.push(' '),
+                    c if c.is_control() => {},
+                    c => out.push(c),
+                }
+            }
+            out
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn default_level_tag_keeps_legacy_layout() {
+        let durylog=DLog::new();
+        assert_eq!(durylog.state.settings().level_to_str(Level::Error), "ERROR ");
+        assert_eq!(durylog.state.settings().level_to_str(Level::Warn), "WARN  ");
+    }
+
+    #[test]
+    fn custom_level_labels() {
+        let durylog=DLog::new().with_level_labels(LEVEL_LABELS_DEFAULT);
+        assert_eq!(durylog.state.settings().level_to_str(Level::Warn), "WARN  ");
+
+        let durylog=DLog::new().with_level_labels(LEVEL_LABELS_SHORT).with_level_brackets();
+        assert_eq!(durylog.state.settings().level_to_str(Level::Info), "[I] ");
+
+        let durylog=DLog::new().with_level_labels(LEVEL_LABELS_LOWER).with_level_align(DLevelAlign::Right);
+        assert_eq!(durylog.state.settings().level_to_str(Level::Warn), " warn ");
+
+        let durylog=DLog::new().with_level_labels(LEVEL_LABELS_FULL).with_level_align(DLevelAlign::None);
+        assert_eq!(durylog.state.settings().level_to_str(Level::Warn), "WARNING");
+    }
+
+    #[test]
+    fn multiline_policies() {
+        let msg="first\nsecond";
+        assert_eq!(DLog::new().state.settings().format_lines("P : ", msg), "P : first\nsecond");
+        assert_eq!(DLog::new().with_multiline(DMultiline::Repeat).state.settings().format_lines("P : ", msg), "P : first\nP : second");
+        assert_eq!(DLog::new().with_multiline(DMultiline::Indent).state.settings().format_lines("P : ", msg), "P : first\n    second");
+        assert_eq!(DLog::new().with_multiline(DMultiline::Escape).state.settings().format_lines("P : ", msg), "P : first\\nsecond");
+    }
+
+    #[test]
+    fn settings_change_after_child_and_logging() {
+        let memory=crate::DMemory::new(10);
+        let durylog=DLog::new().without_console().with_sink(memory.clone()).with_dedup(Duration::from_secs(1));
+        let db=durylog.child("db");
+        durylog.i("started");
+        // Builders work while child loggers and the ticker thread hold the settings
+        let mut durylog=durylog.with_timestamp_format("T").with_format(DFormat::Logfmt);
+        durylog.with_level(LevelFilter::Warn);
+        db.i("filtered out");
+        db.w("applied to child");
+        assert_eq!(memory.messages(), ["started", "applied to child"]);
+    }
+
+    #[test]
+    fn dedup_suppresses_consecutive_duplicates() {
+        let durylog=DLog::new().without_console().with_dedup(Duration::ZERO);
+        let settings=durylog.state.settings();
+        let record=DRecord::new(Level::Error, "retry");
+        assert!(durylog.state.dedup_check(&settings, &record));
+        assert!(!durylog.state.dedup_check(&settings, &record));
+        assert!(!durylog.state.dedup_check(&settings, &record));
+        assert_eq!(durylog.state.dedup_state.lock().unwrap().as_ref().unwrap().count, 2);
+        assert!(durylog.state.dedup_check(&settings, &DRecord::new(Level::Error, "other")));
+        assert_eq!(durylog.state.dedup_state.lock().unwrap().as_ref().unwrap().count, 0);
+    }
+
+    #[test]
+    fn dedup_timeout_writes_repeated_line_without_new_records() {
+        let (sender, receiver)=std::sync::mpsc::channel();
+        let durylog=DLog::new().without_console().with_timestamp_format("T")
+            .with_dedup(Duration::from_secs(3600))
+            .with_sink(crate::DChannel::formatted(sender));
+        for _ in 0..3 {
+            durylog.e("retry");
         }
-        false
+        durylog.state.tick();
+        assert_eq!(receiver.try_iter().count(), 1);
+
+        clock::advance(Duration::from_secs(3600));
+        durylog.state.tick();
+        assert_eq!(receiver.try_iter().collect::<Vec<String>>(), ["T : ERROR  : previous message repeated 2 times"]);
     }
 
-    fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            self.write(record.level(), record.args().to_string().as_str());
+    #[test]
+    fn rate_limiter_drops_before_formatting_and_writes_summary() {
+        let (sender, receiver)=std::sync::mpsc::channel();
+        let durylog=DLog::new().without_console().with_timestamp_format("T")
+            .with_rate_limiter(DRateLimiter::new().with_target_limit("net", 1.0).with_summary(Duration::from_secs(3600)))
+            .with_sink(crate::DChannel::formatted(sender));
+        let formatted=std::cell::Cell::new(0);
+        for _ in 0..3 {
+            durylog.target("net").log_fmt(Level::Info, format_args!("{}", crate::tests::Counted(&formatted)));
         }
+        durylog.state.tick();
+        assert_eq!(formatted.get(), 1);
+        assert_eq!(receiver.try_iter().collect::<Vec<String>>(), ["T : INFO   : counted"]);
+
+        clock::advance(Duration::from_secs(3600));
+        durylog.state.tick();
+        assert_eq!(receiver.try_iter().collect::<Vec<String>>(), ["T : WARN   : suppressed 2 records from net"]);
     }
 
-    fn flush(&self) {}
+    #[test]
+    fn direct_api_call_sites_are_limited_separately() {
+        let memory=crate::DMemory::new(10);
+        let durylog=DLog::new().without_console().with_sink(memory.clone())
+            .with_rate_limiter(DRateLimiter::new().with_call_site_limit(1.0));
+        for _ in 0..2 {
+            durylog.i("first");
+            durylog.i("second");
+        }
+        assert_eq!(memory.messages(), ["first", "second"]);
+        assert_eq!(memory.records()[0].file.as_deref(), Some(file!()));
+        durylog.flush();
+        let summary=memory.messages();
+        assert!(summary[2].starts_with(&format!("suppressed 1 records from {}:", file!())), "{}", summary[2]);
+    }
+
+    #[test]
+    fn flight_recorder_dumps_on_error() {
+        let path=env::temp_dir().join(format!("durylog-flight-{}.log", std::process::id()));
+        fs::remove_file(&path).ok();
+        let memory=crate::DMemory::new(10);
+        let mut durylog=DLog::new().without_console().with_sink(memory.clone()).with_flight_recorder(2)
+            .with_timestamp_format("T").with_file(path.to_str().unwrap()).unwrap();
+        durylog.with_level(LevelFilter::Info);
+        let metadata=|level| Metadata::builder().level(level).target("test").build();
+        for (level, msg) in [(Level::Trace, "one"), (Level::Debug, "two"), (Level::Debug, "three"), (Level::Info, "info")] {
+            durylog.log(&Record::builder().metadata(metadata(level)).args(format_args!("{}", msg)).build());
+        }
+        assert_eq!(memory.messages(), ["info"]);
+        assert_eq!(durylog.state.flight_recorder.lock().unwrap().len(), 2);
+
+        durylog.log(&Record::builder().metadata(metadata(Level::Error)).args(format_args!("failed")).build());
+        assert!(durylog.state.flight_recorder.lock().unwrap().is_empty());
+        // Dump goes through all outputs
+        assert_eq!(memory.messages(), [
+            "info",
+            "---- flight recorder: last 2 records ----",
+            "two",
+            "three",
+            "---- end of flight recorder ----",
+            "failed",
+        ]);
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+            "T : INFO   : info\n\
+            T : INFO   : ---- flight recorder: last 2 records ----\n\
+            T : DEBUG  : two\n\
+            T : DEBUG  : three\n\
+            T : INFO   : ---- end of flight recorder ----\n\
+            T : ERROR  : failed\n");
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn flight_recorder_applies_target_filter() {
+        let memory=crate::DMemory::new(10);
+        let mut durylog=DLog::new().without_console().with_sink(memory.clone()).with_flight_recorder(10)
+            .with_target_filter("net");
+        durylog.with_level(LevelFilter::Info);
+        durylog.target("net").d("net debug");
+        durylog.target("db").d("db debug");
+        durylog.e("failed");
+        assert_eq!(memory.messages()[1..3], ["net debug", "---- end of flight recorder ----"]);
+    }
+
+    #[test]
+    fn routes_split_records_between_files() {
+        let dir=env::temp_dir().join(format!("durylog-routes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path=|name: &str| dir.join(name).to_str().unwrap().to_string();
+        let durylog=DLog::new().without_console().with_timestamp_format("T")
+            .with_file(&path("app.log")).unwrap()
+            .with_route(DFileRoute::new(&path("errors.log")).with_level(LevelFilter::Warn).with_passthrough()).unwrap()
+            .with_route(DFileRoute::new(&path("audit.log")).with_target("app::audit")).unwrap();
+
+        durylog.target("app::audit").w("denied");
+        durylog.target("app::audit").i("login");
+        durylog.e("failed");
+        durylog.i("started");
+        durylog.flush();
+
+        assert_eq!(fs::read_to_string(path("errors.log")).unwrap(), "T : WARN   : denied\nT : ERROR  : failed\n");
+        assert_eq!(fs::read_to_string(path("audit.log")).unwrap(), "T : WARN   : denied\nT : INFO   : login\n");
+        assert_eq!(fs::read_to_string(path("app.log")).unwrap(), "T : ERROR  : failed\nT : INFO   : started\n");
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn direct_api_respects_filters() {
+        let memory=crate::DMemory::new(10);
+        let mut durylog=DLog::new().without_console().with_sink(memory.clone()).with_target_filter("net");
+        durylog.with_level(LevelFilter::Info);
+        durylog.d("debug");
+        // No target: only level filter applies
+        durylog.i("info");
+        durylog.target("net::tcp").w("net warn");
+        durylog.target("net").d("net debug");
+        durylog.target("db").e("db error");
+        assert_eq!(memory.messages(), ["info", "net warn"]);
+        assert_eq!(memory.records()[1].target, "net::tcp");
+    }
+
+    #[test]
+    fn child_with_context() {
+        let (sender, receiver)=std::sync::mpsc::channel();
+        let mut durylog=DLog::new().without_console().with_timestamp_format("T").with_sink(crate::DChannel::formatted(sender));
+        durylog.enable_target_print(true);
+        let db=durylog.child("db").with_context("request_id", 42);
+        db.i("query");
+        db.child("pool").with_context("conn", 3).d("released");
+        durylog.with_context("user", "bob").i("root");
+        // Child loggers are owned: they can be moved to other threads and outlive their parent
+        let worker=durylog.child("worker");
+        drop(durylog);
+        std::thread::spawn(move || worker.w("done")).join().unwrap();
+
+        assert_eq!(receiver.try_iter().collect::<Vec<String>>(), [
+            "T : INFO   : db : query request_id=42",
+            "T : DEBUG  : db::pool : released request_id=42 conn=3",
+            "T : INFO   : root user=bob",
+            "T : WARN   : worker : done",
+        ]);
+    }
+
+    #[test]
+    fn thread_context_in_all_formats() {
+        let _guard=context::push("request_id", "a b");
+        let mut lines=Vec::new();
+        for format in [DFormat::Text, DFormat::Json, DFormat::Logfmt] {
+            let (sender, receiver)=std::sync::mpsc::channel();
+            let durylog=DLog::new().without_console().with_timestamp_format("T").with_format(format)
+                .with_sink(crate::DChannel::formatted(sender));
+            durylog.i("structured");
+            lines.push(receiver.try_recv().unwrap());
+        }
+
+        assert_eq!(lines[0], "T : INFO   : structured request_id=a b");
+        assert!(lines[1].ends_with(",\"message\":\"structured\",\"fields\":{\"request_id\":\"a b\"}}"), "{}", lines[1]);
+        assert!(lines[2].ends_with(" level=info msg=structured request_id=\"a b\""), "{}", lines[2]);
+    }
+
+    #[test]
+    fn sanitize_control_chars() {
+        let msg="user\nFAKE : ERROR : \x1B[31mred\x1B[0m\x07";
+        assert_eq!(sanitize_str(msg, DSanitize::Off), msg);
+        assert_eq!(sanitize_str(msg, DSanitize::Escape), "user\\nFAKE : ERROR : \\x1b[31mred\\x1b[0m\\x07");
+        assert_eq!(sanitize_str(msg, DSanitize::Strip), "user FAKE : ERROR : red");
+        assert_eq!(sanitize_str("a\x1B]0;title\x07b", DSanitize::Strip), "ab");
+    }
+
+    #[test]
+    fn sanitize_applies_to_fields() {
+        let mut record=DRecord::new(Level::Info, "login");
+        record.fields.push(("user".to_string(), "bob\nroot".to_string()));
+        assert_eq!(DLog::new().state.settings().format_text(&record, DSanitize::Escape), "login user=bob\\nroot");
+    }
+
+    #[test]
+    fn sanitize_applies_to_sinks() {
+        let memory=crate::DMemory::new(10);
+        let durylog=DLog::new().without_console().with_file_sanitize(DSanitize::Strip).with_sink(memory.clone());
+        durylog.with_context("user", "bob\x1B[31m").log_fmt(Level::Info, format_args!("login\nFAKE"));
+        assert_eq!(memory.messages(), ["login FAKE"]);
+        assert_eq!(memory.records()[0].fields, [("user".to_string(), "bob".to_string())]);
+    }
 }
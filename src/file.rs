@@ -0,0 +1,708 @@
+use chrono::Local;
+use log::{Level, LevelFilter};
+use regex::Regex;
+use std::{
+    fs::{self, File, OpenOptions}, io::{self, Write}, path::{Path, PathBuf}, process,
+    sync::{Mutex, atomic::{AtomicBool, AtomicU64, Ordering}}, time::{Duration, Instant},
+};
+use crate::{DLogError, sink::{app_name, hostname}};
+
+/// Default format of `{date}` placeholder.
+const DEFAULT_DATE_FORMAT: &str = "%Y%m%d";
+/// How often `{date}` placeholders are expanded again to detect date change
+const DATES_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Part of a filename template.
+#[derive(Debug, PartialEq)]
+enum DTemplatePart<'a> {
+    /// Literal text
+    Literal(&'a str),
+    /// `{name}` or `{name:arg}` placeholder
+    Placeholder(&'a str, Option<&'a str>),
+}
+
+/// ['return'] ['template'] split in literal text and placeholders.
+/// Unknown or unterminated placeholders are kept as literal text.
+fn parse_template(template: &str) -> Vec<DTemplatePart<'_>> {
+    let mut parts=Vec::new();
+    let mut rest=template;
+    while let Some(start)=rest.find('{') {
+        let Some(len)=rest[start..].find('}') else { break };
+        let (name, arg)=match rest[start + 1..start + len].split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (&rest[start + 1..start + len], None),
+        };
+        if !["app", "date", "pid", "hostname"].contains(&name) {
+            parts.push(DTemplatePart::Literal(&rest[..start + len + 1]));
+        } else {
+            if start > 0 {
+                parts.push(DTemplatePart::Literal(&rest[..start]));
+            }
+            parts.push(DTemplatePart::Placeholder(name, arg));
+        }
+        rest=&rest[start + len + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(DTemplatePart::Literal(rest));
+    }
+    parts
+}
+
+/// ['return'] ['template'] with placeholders replaced:
+/// - `{app}`: executable name.
+/// - `{date}` or `{date:FORMAT}`: local date (chrono format, default `%Y%m%d`).
+/// - `{pid}`: process id.
+/// - `{hostname}`: host name.
+pub(crate) fn expand_template(template: &str) -> PathBuf {
+    let mut expanded=String::new();
+    for part in parse_template(template) {
+        match part {
+            DTemplatePart::Literal(text) => expanded.push_str(text),
+            DTemplatePart::Placeholder("app", _) => expanded.push_str(&app_name()),
+            DTemplatePart::Placeholder("date", format) => expanded.push_str(&Local::now().format(format.unwrap_or(DEFAULT_DATE_FORMAT)).to_string()),
+            DTemplatePart::Placeholder("pid", _) => expanded.push_str(&process::id().to_string()),
+            DTemplatePart::Placeholder(_, _) => expanded.push_str(&hostname()),
+        }
+    }
+    PathBuf::from(expanded)
+}
+
+/// ['return'] formats of `{date}` placeholders of ['template'].
+fn date_formats(template: &str) -> Vec<String> {
+    parse_template(template).into_iter()
+        .filter_map(|part| match part {
+            DTemplatePart::Placeholder("date", format) => Some(format.unwrap_or(DEFAULT_DATE_FORMAT).to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// ['return'] dates of ['formats'] expanded now, to detect date change.
+fn expand_dates(formats: &[String]) -> String {
+    let now=Local::now();
+    formats.iter()
+        .map(|format| now.format(format).to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// ['return'] regex matching names that file name of ['template'] expands to in this process:
+/// `{app}` and `{hostname}` match their value, `{pid}` any number and `{date}` any date of its format.
+fn template_regex(template: &str) -> Result<Regex, regex::Error> {
+    let name=template.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut pattern=String::from("^");
+    for part in parse_template(name) {
+        match part {
+            DTemplatePart::Literal(text) => pattern.push_str(&regex::escape(text)),
+            DTemplatePart::Placeholder("app", _) => pattern.push_str(&regex::escape(&app_name())),
+            DTemplatePart::Placeholder("date", format) => pattern.push_str(&date_regex(format.unwrap_or(DEFAULT_DATE_FORMAT))),
+            DTemplatePart::Placeholder("pid", _) => pattern.push_str(r"\d+"),
+            DTemplatePart::Placeholder(_, _) => pattern.push_str(&regex::escape(&hostname())),
+        }
+    }
+    Regex::new(&(pattern + "$"))
+}
+
+/// ['return'] regex pattern matching dates formatted with chrono ['format'].
+fn date_regex(format: &str) -> String {
+    let mut pattern=String::new();
+    let mut chars=format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            pattern.push_str(&regex::escape(&c.to_string()));
+            continue;
+        }
+        // Padding modifier (%-d, %_d, %0d) makes width variable
+        let mut spec=chars.next();
+        let padding=matches!(spec, Some('-' | '_' | '0'));
+        if padding {
+            spec=chars.next();
+        }
+        let digits=|n: usize| if padding { format!(r"[ \d]{{1,{}}}", n) } else { format!(r"\d{{{}}}", n) };
+        match spec {
+            Some('Y') => pattern.push_str(&digits(4)),
+            Some('C' | 'y' | 'm' | 'd' | 'H' | 'I' | 'M' | 'S' | 'U' | 'W' | 'V' | 'g') => pattern.push_str(&digits(2)),
+            Some('j') => pattern.push_str(&digits(3)),
+            Some('e' | 'k' | 'l') => pattern.push_str(r"[ \d]\d"),
+            Some('u' | 'w') => pattern.push_str(r"\d"),
+            Some('G' | 's') => pattern.push_str(r"\d+"),
+            Some('F') => pattern.push_str(r"\d{4}-\d{2}-\d{2}"),
+            Some('D' | 'x') => pattern.push_str(r"\d{2}/\d{2}/\d{2}"),
+            Some('T' | 'X') => pattern.push_str(r"\d{2}:\d{2}:\d{2}"),
+            Some('R') => pattern.push_str(r"\d{2}:\d{2}"),
+            Some('b' | 'h' | 'a') => pattern.push_str("[A-Za-z]{3}"),
+            Some('%') => pattern.push('%'),
+            // Other specifiers (names, fractions, time zones...)
+            _ => pattern.push_str("[0-9A-Za-z]+"),
+        }
+    }
+    pattern
+}
+
+/// Lock ['file'] with an exclusive advisory lock, waiting for other processes to release it.
+#[cfg(feature = "file-lock")]
+fn lock(file: &File) -> io::Result<()> {
+    file.lock()
+}
+
+/// Lock ['file']: not supported without `file-lock` feature.
+#[cfg(not(feature = "file-lock"))]
+fn lock(_file: &File) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Release lock of ['file'].
+#[cfg(feature = "file-lock")]
+fn unlock(file: &File) -> io::Result<()> {
+    file.unlock()
+}
+
+/// Release lock of ['file']: not supported without `file-lock` feature.
+#[cfg(not(feature = "file-lock"))]
+fn unlock(_file: &File) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// ['return'] true if ['name'] is an archive (`<stem>-<timestamp>[.<extension>]`, see [`DFile::rotate_files()`])
+/// of a file whose name matches ['matcher'].
+fn is_archive(matcher: &Regex, name: &str) -> bool {
+    let path=Path::new(name);
+    let stem=path.file_stem().unwrap_or_default().to_string_lossy();
+    let Some(live_stem)=stem.len().checked_sub(21).and_then(|len| stem.get(..len)) else { return false };
+    if !stem[live_stem.len()..].starts_with('-') || !stem[live_stem.len() + 1..].bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    match path.extension() {
+        Some(extension) => matcher.is_match(&format!("{}.{}", live_stem, extension.to_string_lossy())),
+        None => matcher.is_match(live_stem),
+    }
+}
+
+/// Options used when log files are created.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DFileOptions {
+    /// Create missing parent directories
+    pub(crate) create_dirs: bool,
+    /// Unix permission bits of log files created by the logger, kept by their archives (default umask if None)
+    pub(crate) mode: Option<u32>,
+    /// Hold an advisory lock on file while writing and rotating, to share it between processes
+    pub(crate) lock: bool,
+}
+
+/// Currently opened file.
+#[derive(Debug)]
+struct DOpenedFile {
+    /// File name (expanded template)
+    path: PathBuf,
+    /// Opened file
+    file: File,
+    /// File has been created by this logger (not already existing)
+    created: bool,
+    /// `{date}` placeholders of file name as expanded at open
+    dates: String,
+    /// Last time `{date}` placeholders have been expanded again
+    dates_checked: Instant,
+}
+
+/// Log file with size based rotation and retention.
+#[derive(Debug)]
+pub(crate) struct DFile {
+    /// File name template
+    template: String,
+    /// Names this template expands to
+    matcher: Regex,
+    /// Formats of `{date}` placeholders of template
+    date_formats: Vec<String>,
+    /// Opened file
+    opened: Mutex<DOpenedFile>,
+    /// Max size of file before rotation (0 = no limit)
+    max_file_size: AtomicU64,
+    /// Max number of files to keep, current one included (0 = no limit)
+    max_files_count: AtomicU64,
+    /// Creation options
+    options: Mutex<DFileOptions>,
+    /// Locking has failed once (e.g. not supported by file system): file is written without lock
+    lock_failed: AtomicBool,
+}
+
+impl DFile {
+    /// Open (or create) file named from ['template'] (see [`expand_template()`]) for append, due to ['options'].
+    pub(crate) fn open(template: &str, options: DFileOptions) -> Result<Self, DLogError> {
+        let date_formats=date_formats(template);
+        Ok(Self {
+            template: template.to_string(),
+            matcher: template_regex(template).map_err(DLogError::Regex)?,
+            opened: Mutex::new(Self::open_file(template, &date_formats, options).map_err(DLogError::Err)?),
+            date_formats,
+            max_file_size: AtomicU64::new(0), // no limits
+            max_files_count: AtomicU64::new(0), // no limits
+            options: Mutex::new(options),
+            lock_failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Open a new file expanding template again, in place of ['opened'].
+    fn reopen(&self, opened: &mut DOpenedFile) -> io::Result<()> {
+        // Dropping old handle releases its lock
+        *opened=Self::open_file(&self.template, &self.date_formats, self.options())?;
+        Ok(())
+    }
+
+    /// Open file named from ['template'] (whose `{date}` placeholders have ['date_formats']) with options
+    /// enabled: read, write, create, append.
+    /// Parent directories are created as required by ['options']; permissions are set only if the file is
+    /// created here, never on existing files.
+    fn open_file(template: &str, date_formats: &[String], options: DFileOptions) -> io::Result<DOpenedFile> {
+        let path=expand_template(template);
+        if options.create_dirs {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let mut open_options=OpenOptions::new();
+        open_options.read(true).write(true).create(true).append(true);
+        #[cfg(unix)]
+        if let Some(mode) = options.mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            open_options.mode(mode);
+        }
+        // Try to create it first, to know if it already exists
+        let (file, created)=match open_options.clone().create_new(true).open(&path) {
+            Ok(file) => (file, true),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (open_options.open(&path)?, false),
+            Err(err) => return Err(err),
+        };
+        if created {
+            // Mode on open is masked by umask
+            Self::set_mode(&path, options)?;
+        }
+        Ok(DOpenedFile { path, file, created, dates: expand_dates(date_formats), dates_checked: Instant::now() })
+    }
+
+    /// Set permissions of ['path'] if required by ['options'] (only on unix).
+    fn set_mode(path: &Path, options: DFileOptions) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(mode) = options.mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _=(path, options);
+        Ok(())
+    }
+
+    /// ['return'] current file name.
+    pub(crate) fn path(&self) -> PathBuf {
+        self.opened.lock().unwrap_or_else(|e| e.into_inner()).path.clone()
+    }
+
+    /// ['return'] current creation options.
+    fn options(&self) -> DFileOptions {
+        *self.options.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Use ['options'] from now on: permissions are also set on current file, if it has been created by this logger.
+    pub(crate) fn set_options(&self, options: DFileOptions) -> Result<(), DLogError> {
+        *self.options.lock().unwrap_or_else(|e| e.into_inner())=options;
+        let opened=self.opened.lock().unwrap_or_else(|e| e.into_inner());
+        if opened.created {
+            Self::set_mode(&opened.path, options).map_err(DLogError::Err)?;
+        }
+        Ok(())
+    }
+
+    /// Rotate file when it exceeds ['max_file_size'] bytes, keeping at most ['max_files_count'] files (0 = no limit).
+    pub(crate) fn set_rotation(&self, max_file_size: u64, max_files_count: u64) {
+        self.max_file_size.store(max_file_size, Ordering::Relaxed);
+        self.max_files_count.store(max_files_count, Ordering::Relaxed);
+    }
+
+    /// Write string ['msg'] into file, then make a files rotation/delete due to the settings.
+    /// 
+    /// With lock option, the file is locked meanwhile, and reopened first if another process has rotated it.
+    /// If locking fails (e.g. the file system doesn't support it), the error is reported once on stderr and
+    /// the file is written without lock from then on.
+    pub(crate) fn write_file(&self, msg: &str) -> Result<usize, DLogError> {
+        let mut opened=self.opened.lock().unwrap_or_else(|e| e.into_inner());
+        let locked=self.lock(&opened);
+
+        let result=self.write_and_check(&mut opened, msg, locked);
+        if locked {
+            // Current handle may be a new one after reopen or rotation: old ones are unlocked on close
+            unlock(&opened.file).ok();
+        }
+        result
+    }
+
+    /// Lock ['opened'] file if required by options.
+    /// ['return'] true if it is locked.
+    fn lock(&self, opened: &DOpenedFile) -> bool {
+        if !self.options().lock || self.lock_failed.load(Ordering::Relaxed) {
+            return false;
+        }
+        match lock(&opened.file) {
+            Ok(()) => true,
+            Err(err) => {
+                if !self.lock_failed.swap(true, Ordering::Relaxed) {
+                    eprintln!("durylog: cannot lock {}, writing it without lock: {}", opened.path.display(), err);
+                }
+                false
+            },
+        }
+    }
+
+    /// Write string ['msg'] into ['opened'] file (['locked'] if lock is held), then make a files rotation/delete
+    /// due to the settings.
+    /// 
+    /// A new file is opened first if date in file name has changed, or if another process has rotated it.
+    fn write_and_check(&self, opened: &mut DOpenedFile, msg: &str, locked: bool) -> Result<usize, DLogError> {
+        if self.is_date_changed(opened) || (locked && Self::is_rotated(opened)) {
+            self.reopen(opened).map_err(DLogError::Err)?;
+            if locked {
+                lock(&opened.file).map_err(DLogError::Err)?;
+            }
+        }
+
+        let b_written=opened.file.write(format!("{}\n", msg).as_bytes()).map_err(DLogError::Err)?;
+        self.check_storage(opened)?;
+        Ok(b_written)
+    }
+
+    /// ['return'] true if date in file name of ['opened'] has changed. Dates are expanded again at most once
+    /// per ['DATES_CHECK_INTERVAL'], and never if template has no `{date}` placeholder.
+    fn is_date_changed(&self, opened: &mut DOpenedFile) -> bool {
+        if self.date_formats.is_empty() || opened.dates_checked.elapsed() < DATES_CHECK_INTERVAL {
+            return false;
+        }
+        opened.dates_checked=Instant::now();
+        opened.dates != expand_dates(&self.date_formats)
+    }
+
+    /// Make a files rotation/delete due to the settings.
+    fn check_storage(&self, opened: &mut DOpenedFile) -> Result<(), DLogError> {
+        // Check for current file size
+        let metadata=opened.file.metadata().map_err(DLogError::Err)?;
+        let max_file_size=self.max_file_size.load(Ordering::Relaxed);
+        if max_file_size > 0 && metadata.len() > max_file_size {
+            // exceed max size
+            self.rotate_files(opened)?;
+        }
+        Ok(())
+    }
+
+    /// ['return'] true if ['opened'] file is no more at its path (renamed or deleted by another process).
+    fn is_rotated(opened: &DOpenedFile) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            match (fs::metadata(&opened.path), opened.file.metadata()) {
+                (Ok(current), Ok(file)) => current.dev() != file.dev() || current.ino() != file.ino(),
+                _ => true,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _=opened;
+            false
+        }
+    }
+
+    /// Flush file.
+    pub(crate) fn flush(&self) {
+        self.opened.lock().unwrap_or_else(|e| e.into_inner()).file.flush().ok();
+    }
+
+    /// Rename current file as an archive named `<stem>-<timestamp>.<extension>`, open a new one (expanding
+    /// template again) in place of ['opened'], then delete oldest archives exceeding ['max_files_count'].
+    /// N.B. The old file is closed (and so unlocked) only at the end.
+    fn rotate_files(&self, opened: &mut DOpenedFile) -> Result<(), DLogError> {
+        let stem=opened.path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut archive=format!("{}-{}", stem, Local::now().format("%Y%m%d%H%M%S%6f"));
+        if let Some(extension) = opened.path.extension() {
+            archive.push('.');
+            archive.push_str(&extension.to_string_lossy());
+        }
+        let archive=opened.path.with_file_name(archive);
+        // Archive keeps permissions of current file
+        fs::rename(&opened.path, &archive).map_err(DLogError::Err)?;
+        let new=Self::open_file(&self.template, &self.date_formats, self.options()).map_err(DLogError::Err)?;
+        let _old=std::mem::replace(opened, new);
+
+        // Check for max files count
+        let max_files_count=self.max_files_count.load(Ordering::Relaxed);
+        if max_files_count > 0 {
+            // Delete all files that exceeds max files count
+            let files_list=self.get_files(&opened.path)?;
+            let keep=max_files_count.saturating_sub(1) as usize;
+            for path in files_list.iter().take(files_list.len().saturating_sub(keep)) {
+                fs::remove_file(path).map_err(DLogError::Err)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// ['return'] a vector containing archives of this file in the directory of ['current'] file, oldest first.
+    /// 
+    /// Only archives created by rotation of a name this template expands to are listed: other files, and
+    /// current files of other templates, are never listed.
+    fn get_files(&self, current: &Path) -> Result<Vec<PathBuf>, DLogError> {
+        let dir=match current.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut files_list: Vec<(std::time::SystemTime, PathBuf)>=fs::read_dir(dir).map_err(DLogError::Err)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.file_name() != current.file_name())
+            .filter(|path| path.file_name().is_some_and(|name| is_archive(&self.matcher, &name.to_string_lossy())))
+            .filter_map(|path| Some((fs::metadata(&path).and_then(|m| m.modified()).ok()?, path)))
+            .collect();
+        files_list.sort();
+        Ok(files_list.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+/// File that receives records selected by level and/or target, instead of the main log file.
+///
+/// Routes are checked in the order they are added: a record is written in the first matching route and
+/// not in the main file, unless the route is set with [`DFileRoute::with_passthrough()`].
+///
+/// The file is opened by [`DLog::with_route()`](crate::DLog::with_route), with the same creation options of
+/// the main file.
+///
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DFileRoute, DLog, LevelFilter};
+///
+/// let durylog=DLog::new()
+///     .with_file("app.log").unwrap()
+///     .with_route(DFileRoute::new("errors.log").with_level(LevelFilter::Warn).with_passthrough()).unwrap()
+///     .with_route(DFileRoute::new("audit.log").with_target("my_app::audit")).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DFileRoute {
+    /// Output file name template
+    filename: String,
+    /// Max size of file before rotation (0 = no limit)
+    max_file_size: u64,
+    /// Max number of files to keep (0 = no limit)
+    max_files_count: u64,
+    /// Most verbose level written
+    level: LevelFilter,
+    /// Target prefix written (all if None)
+    target: Option<String>,
+    /// Records are written also in following routes and main file
+    passthrough: bool,
+}
+
+impl DFileRoute {
+    /// Create a route to ['filename'] (a template, see [`DLog::with_file()`](crate::DLog::with_file)) for all records.
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            max_file_size: 0, // no limits
+            max_files_count: 0, // no limits
+            level: LevelFilter::Trace,
+            target: None,
+            passthrough: false,
+        }
+    }
+
+    /// Route only records of ['level'] or more severe (e.g. `LevelFilter::Warn` for Error and Warn records).
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level=level;
+        self
+    }
+
+    /// Route only records whose target starts with ['target'].
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.target=Some(target.to_string());
+        self
+    }
+
+    /// Rotate this file when it exceeds ['max_file_size'] bytes (0 = no limit).
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size=max_file_size;
+        self
+    }
+
+    /// Keep at most ['max_files_count'] files on rotation, current one included (0 = no limit).
+    pub fn with_max_files_count(mut self, max_files_count: u64) -> Self {
+        self.max_files_count=max_files_count;
+        self
+    }
+
+    /// Write routed records also in following routes and main file.
+    pub fn with_passthrough(mut self) -> Self {
+        self.passthrough=true;
+        self
+    }
+
+    /// ['return'] true if a record of ['level'] and ['target'] is routed here.
+    pub(crate) fn matches(&self, level: Level, target: &str) -> bool {
+        level <= self.level && self.target.as_ref().is_none_or(|t| target.starts_with(t.as_str()))
+    }
+
+    /// ['return'] true if routed records continue to following routes and main file.
+    pub(crate) fn is_passthrough(&self) -> bool {
+        self.passthrough
+    }
+
+    /// ['return'] route file opened due to ['options'].
+    pub(crate) fn open(&self, options: DFileOptions) -> Result<DFile, DLogError> {
+        let file=DFile::open(&self.filename, options)?;
+        file.set_rotation(self.max_file_size, self.max_files_count);
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn rotation_keeps_max_files() {
+        let dir=env::temp_dir().join(format!("durylog-rotation-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file=DFile::open(dir.join("app.log").to_str().unwrap(), DFileOptions::default()).unwrap();
+        file.set_rotation(10, 3);
+
+        for i in 0..6 {
+            file.write_file(&format!("line number {}", i)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        assert_eq!(file.get_files(&dir.join("app.log")).unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("app.log")).unwrap(), "");
+        let last=file.get_files(&dir.join("app.log")).unwrap().pop().unwrap();
+        assert_eq!(fs::read_to_string(last).unwrap(), "line number 5\n");
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(feature = "file-lock")]
+    #[test]
+    fn locked_files_share_rotation() {
+        let dir=env::temp_dir().join(format!("durylog-lock-{}", std::process::id()));
+        let options=DFileOptions { create_dirs: true, mode: None, lock: true };
+        // Two handles of the same file, like two processes
+        let writers: Vec<DFile>=(0..2).map(|_| {
+            let file=DFile::open(dir.join("shared.log").to_str().unwrap(), options).unwrap();
+            file.set_rotation(200, 0);
+            file
+        }).collect();
+
+        std::thread::scope(|scope| {
+            for (id, writer) in writers.iter().enumerate() {
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        writer.write_file(&format!("writer {} line {:02}", id, i)).unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut lines: Vec<String>=writers[0].get_files(&dir.join("shared.log")).unwrap().into_iter().chain([dir.join("shared.log")])
+            .flat_map(|path| fs::read_to_string(path).unwrap().lines().map(String::from).collect::<Vec<_>>())
+            .collect();
+        lines.sort();
+        let mut expected: Vec<String>=(0..2).flat_map(|id| (0..50).map(move |i| format!("writer {} line {:02}", id, i))).collect();
+        expected.sort();
+        assert_eq!(lines, expected);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn templates_expand_and_match() {
+        let template="logs/{app}-{date:%Y.%m}-{pid}.log";
+        let expanded=expand_template(template);
+        let exe=env::current_exe().unwrap().file_stem().unwrap().to_string_lossy().into_owned();
+        assert_eq!(expanded, PathBuf::from(format!("logs/{}-{}-{}.log", exe, Local::now().format("%Y.%m"), process::id())));
+        assert_eq!(expand_template("{hostname}.log"), PathBuf::from(format!("{}.log", hostname())));
+        assert_eq!(expand_template("{unknown}-{date}.log"), PathBuf::from(format!("{{unknown}}-{}.log", Local::now().format("%Y%m%d"))));
+
+        let matcher=template_regex(template).unwrap();
+        assert!(matcher.is_match(&format!("{}-2023.01-42.log", exe)));
+        assert!(!matcher.is_match(&format!("{}-2023-01-42.log", exe)));
+        assert!(!matcher.is_match("other-2023.01-42.log"));
+        assert!(is_archive(&matcher, &format!("{}-2023.01-42-20230102180127123456.log", exe)));
+        assert!(!is_archive(&matcher, &format!("{}-2023.01-42.log", exe)));
+        assert!(!is_archive(&matcher, &format!("{}-2023.01-42-20230102180127123456.log.gz", exe)));
+        let matcher=template_regex("app.log").unwrap();
+        assert!(is_archive(&matcher, "app-20230102180127123456.log"));
+        assert!(!is_archive(&matcher, "app-errors.log"));
+        assert!(!is_archive(&matcher, "app-errors-20230102180127123456.log"));
+        let matcher=template_regex("{hostname}.log").unwrap();
+        assert!(!is_archive(&matcher, "other-20230102180127123456.log"));
+        assert_eq!(date_regex("%Y-%m-%d_%-H"), r"\d{4}\-\d{2}\-\d{2}_[ \d]{1,2}");
+    }
+
+    #[test]
+    fn retention_keeps_unrelated_files() {
+        let dir=env::temp_dir().join(format!("durylog-retention-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("other.log"), "unrelated\n").unwrap();
+        let route=DFile::open(dir.join("{pid}-errors.log").to_str().unwrap(), DFileOptions::default()).unwrap();
+        route.write_file("route").unwrap();
+        let file=DFile::open(dir.join("{hostname}.log").to_str().unwrap(), DFileOptions::default()).unwrap();
+        file.set_rotation(1, 1);
+
+        for i in 0..3 {
+            file.write_file(&format!("line number {}", i)).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(dir.join("other.log")).unwrap(), "unrelated\n");
+        assert_eq!(fs::read_to_string(route.path()).unwrap(), "route\n");
+        assert!(file.get_files(&file.path()).unwrap().is_empty());
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn date_change_opens_new_file() {
+        let dir=env::temp_dir().join(format!("durylog-date-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file=DFile::open(dir.join("app-{date:%H%M%S}.log").to_str().unwrap(), DFileOptions::default()).unwrap();
+        let first=file.path();
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        file.write_file("next second").unwrap();
+
+        assert_ne!(file.path(), first);
+        assert_eq!(fs::read_to_string(first).unwrap(), "");
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "next second\n");
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn route_matches_level_and_target() {
+        let route=DFileRoute::new("audit.log").with_level(LevelFilter::Warn).with_target("app::audit");
+        assert!(route.matches(Level::Error, "app::audit::login"));
+        assert!(!route.matches(Level::Info, "app::audit"));
+        assert!(!route.matches(Level::Warn, "app::net"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn creates_dirs_and_sets_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir=env::temp_dir().join(format!("durylog-mode-{}", std::process::id()));
+        let options=DFileOptions { create_dirs: true, mode: Some(0o640), lock: false };
+        let file=DFile::open(dir.join("nested/app.log").to_str().unwrap(), options).unwrap();
+        file.set_rotation(1, 0);
+        file.write_file("rotate").unwrap();
+
+        let mode=|path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.join("nested/app.log")), 0o640);
+        assert_eq!(mode(&file.get_files(&dir.join("nested/app.log")).unwrap()[0]), 0o640);
+
+        // Options set after open apply to a file created by the logger, but never to an existing one
+        let file=DFile::open(dir.join("late.log").to_str().unwrap(), DFileOptions::default()).unwrap();
+        file.set_options(options).unwrap();
+        assert_eq!(mode(&dir.join("late.log")), 0o640);
+        fs::write(dir.join("existing.log"), "").unwrap();
+        fs::set_permissions(dir.join("existing.log"), fs::Permissions::from_mode(0o600)).unwrap();
+        let file=DFile::open(dir.join("existing.log").to_str().unwrap(), options).unwrap();
+        file.set_options(options).unwrap();
+        assert_eq!(mode(&dir.join("existing.log")), 0o600);
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    io::Write,
+    net::{TcpStream, ToSocketAddrs, UdpSocket},
+    process,
+    sync::{atomic::{AtomicU64, Ordering}, Mutex},
+};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::{hostname, json_str, udp_connect, DSink};
+use crate::syslog::severity;
+
+/// Default max udp chunk size (fits in a LAN datagram).
+pub const GELF_CHUNK_SIZE_LAN: usize = 8154;
+/// Max udp chunk size for WAN.
+pub const GELF_CHUNK_SIZE_WAN: usize = 1420;
+/// Chunked GELF magic bytes.
+const GELF_CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+/// Chunked GELF header size (magic, message id, sequence number, sequence count).
+const GELF_CHUNK_HEADER: usize = 12;
+/// Max number of chunks for a message.
+const GELF_MAX_CHUNKS: usize = 128;
+
+/// Where GELF messages are sent.
+#[derive(Debug)]
+enum DGelfTransport {
+    /// Udp socket (chunked)
+    Udp(UdpSocket),
+    /// Tcp stream (null byte delimited)
+    Tcp(Mutex<TcpStream>),
+}
+
+/// Sink that sends records to Graylog as GELF 1.1 messages.
+/// 
+/// Fields mapping:
+/// - `host`: this host name.
+/// - `short_message`: first line of message, `full_message`: whole message if it has more lines.
+/// - `timestamp`: seconds since epoch, with decimals.
+/// - `level`: syslog severity.
+/// - `_target`, `_file`, `_line`: record source.
+/// - `_<key>`: one field for each key-value.
+/// 
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DLog, DGelf};
+/// 
+/// let durylog=DLog::new()
+///     .with_sink(DGelf::udp("graylog.example.com:12201").unwrap());
+/// ```
+#[derive(Debug)]
+pub struct DGelf {
+    /// Socket
+    transport: DGelfTransport,
+    /// Host field
+    host: String,
+    /// Max udp chunk size
+    chunk_size: usize,
+    /// Compress udp messages
+    #[cfg(feature = "compression")]
+    compression_enabled: bool,
+    /// Counter for chunked messages ids
+    message_counter: AtomicU64,
+}
+
+impl DGelf {
+    /// Create a sink that sends to udp ['addr'] (e.g. "127.0.0.1:12201").
+    pub fn udp<A: ToSocketAddrs>(addr: A) -> Result<Self, DLogError> {
+        let socket=udp_connect(addr).map_err(DLogError::Err)?;
+        Ok(Self::with_transport(DGelfTransport::Udp(socket)))
+    }
+
+    /// Create a sink that sends to tcp ['addr'] (e.g. "127.0.0.1:12201").
+    pub fn tcp<A: ToSocketAddrs>(addr: A) -> Result<Self, DLogError> {
+        let stream=TcpStream::connect(addr).map_err(DLogError::Err)?;
+        Ok(Self::with_transport(DGelfTransport::Tcp(Mutex::new(stream))))
+    }
+
+    /// Create a sink with default settings on ['transport'].
+    fn with_transport(transport: DGelfTransport) -> Self {
+        Self {
+            transport,
+            host: hostname(),
+            chunk_size: GELF_CHUNK_SIZE_LAN,
+            #[cfg(feature = "compression")]
+            compression_enabled: false,
+            message_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Set host field. Default is this host name.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host=host.to_string();
+        self
+    }
+
+    /// Set max udp chunk size. Default is [`GELF_CHUNK_SIZE_LAN`].
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size=size.max(GELF_CHUNK_HEADER + 1);
+        self
+    }
+
+    /// Compress udp messages with gzip (tcp messages are never compressed).
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self) -> Self {
+        self.compression_enabled=true;
+        self
+    }
+
+    /// ['return'] ['record'] as GELF json.
+    fn format(&self, record: &DRecord) -> String {
+        let (short, full)=match record.message.split_once('\n') {
+            Some((first, _)) => (first, Some(&record.message)),
+            None => (record.message.as_str(), None),
+        };
+        let timestamp=record.timestamp.timestamp_micros() as f64 / 1_000_000.0;
+
+        let mut json=String::from("{\"version\":\"1.1\"");
+        json+=&format!(",\"host\":{}", json_str(&self.host));
+        json+=&format!(",\"short_message\":{}", json_str(short));
+        if let Some(full) = full {
+            json+=&format!(",\"full_message\":{}", json_str(full));
+        }
+        json+=&format!(",\"timestamp\":{:.6}", timestamp);
+        json+=&format!(",\"level\":{}", severity(record.level));
+        if !record.target.is_empty() {
+            json+=&format!(",\"_target\":{}", json_str(&record.target));
+        }
+        if let Some(file) = &record.file {
+            json+=&format!(",\"_file\":{}", json_str(file));
+        }
+        if let Some(line) = record.line {
+            json+=&format!(",\"_line\":{}", line);
+        }
+        for (key, value) in &record.fields {
+            if let Some(name) = field_name(key) {
+                json+=&format!(",{}:{}", json_str(&name), json_str(value));
+            }
+        }
+        json.push('}');
+        json
+    }
+
+    /// Send ['payload'] on udp ['socket'], split in chunks if needed.
+    fn send_udp(&self, socket: &UdpSocket, payload: &[u8]) -> Result<(), DLogError> {
+        if payload.len() <= self.chunk_size {
+            socket.send(payload).map_err(DLogError::Err)?;
+            return Ok(());
+        }
+
+        let data_size=self.chunk_size - GELF_CHUNK_HEADER;
+        let count=payload.len().div_ceil(data_size);
+        if count > GELF_MAX_CHUNKS {
+            return Err(DLogError::Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "GELF message too big")));
+        }
+
+        let id=self.message_id();
+        for (seq, data) in payload.chunks(data_size).enumerate() {
+            let mut chunk=Vec::with_capacity(GELF_CHUNK_HEADER + data.len());
+            chunk.extend_from_slice(&GELF_CHUNK_MAGIC);
+            chunk.extend_from_slice(&id);
+            chunk.push(seq as u8);
+            chunk.push(count as u8);
+            chunk.extend_from_slice(data);
+            socket.send(&chunk).map_err(DLogError::Err)?;
+        }
+        Ok(())
+    }
+
+    /// ['return'] a new chunked message id.
+    fn message_id(&self) -> [u8; 8] {
+        let counter=self.message_counter.fetch_add(1, Ordering::Relaxed);
+        let nanos=std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default();
+        (nanos ^ counter.rotate_left(32) ^ (process::id() as u64)).to_be_bytes()
+    }
+
+    /// ['return'] ['json'] compressed if enabled.
+    fn compress(&self, json: String) -> Vec<u8> {
+        #[cfg(feature = "compression")]
+        if self.compression_enabled {
+            let mut encoder=flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            if encoder.write_all(json.as_bytes()).is_ok() {
+                if let Ok(compressed) = encoder.finish() {
+                    return compressed;
+                }
+            }
+        }
+        json.into_bytes()
+    }
+}
+
+impl DSink for DGelf {
+    fn write(&self, record: &DRecord, _line: &str) -> Result<(), DLogError> {
+        let json=self.format(record);
+        match &self.transport {
+            DGelfTransport::Udp(socket) => self.send_udp(socket, &self.compress(json)),
+            DGelfTransport::Tcp(stream) => {
+                let mut stream=stream.lock().unwrap_or_else(|e| e.into_inner());
+                stream.write_all(json.as_bytes())
+                    .and_then(|_| stream.write_all(&[0]))
+                    .map_err(DLogError::Err)
+            },
+        }
+    }
+}
+
+/// ['return'] GELF additional field name for ['key']: '_' followed by key with chars other than
+/// letters, digits, '_', '.', '-' replaced by '_'. None for reserved `_id`.
+fn field_name(key: &str) -> Option<String> {
+    let name: String=key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    (!name.is_empty() && name != "id").then(|| String::from("_") + &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::{io::Read, net::TcpListener};
+
+    #[test]
+    fn udp_message() {
+        let server=UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink=DGelf::udp(server.local_addr().unwrap()).unwrap().with_host("host");
+        let mut record=DRecord::new(Level::Error, "failed\n\"details\"");
+        record.fields.push(("user id".to_string(), "42".to_string()));
+        sink.write(&record, "").unwrap();
+
+        let mut buf=[0; 2048];
+        let len=server.recv(&mut buf).unwrap();
+        let msg=String::from_utf8_lossy(&buf[..len]);
+        assert!(msg.starts_with("{\"version\":\"1.1\",\"host\":\"host\",\"short_message\":\"failed\",\"full_message\":\"failed\\n\\\"details\\\"\",\"timestamp\":"), "{}", msg);
+        assert!(msg.ends_with(",\"level\":3,\"_user_id\":\"42\"}"), "{}", msg);
+    }
+
+    #[test]
+    fn udp_ipv6() {
+        // Skip where IPv6 loopback is not available
+        let Ok(server)=UdpSocket::bind("[::1]:0") else { return };
+        let sink=DGelf::udp(server.local_addr().unwrap()).unwrap().with_host("host");
+        sink.write(&DRecord::new(Level::Info, "ready"), "").unwrap();
+
+        let mut buf=[0; 2048];
+        let len=server.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).contains("\"short_message\":\"ready\""));
+    }
+
+    #[test]
+    fn udp_chunks() {
+        let server=UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink=DGelf::udp(server.local_addr().unwrap()).unwrap().with_chunk_size(64);
+        let record=DRecord::new(Level::Info, &"x".repeat(200));
+        sink.write(&record, "").unwrap();
+
+        let mut payload=Vec::new();
+        let mut buf=[0; 64];
+        let mut count=usize::MAX;
+        let mut seq=0;
+        while seq < count {
+            let len=server.recv(&mut buf).unwrap();
+            assert_eq!(buf[..2], GELF_CHUNK_MAGIC);
+            assert_eq!(buf[10] as usize, seq);
+            count=buf[11] as usize;
+            payload.extend_from_slice(&buf[GELF_CHUNK_HEADER..len]);
+            seq+=1;
+        }
+        assert_eq!(String::from_utf8(payload).unwrap(), sink.format(&record));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn udp_compressed() {
+        let server=UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink=DGelf::udp(server.local_addr().unwrap()).unwrap().with_compression();
+        let record=DRecord::new(Level::Info, "compressed");
+        sink.write(&record, "").unwrap();
+
+        let mut buf=[0; 2048];
+        let len=server.recv(&mut buf).unwrap();
+        let mut json=String::new();
+        flate2::read::GzDecoder::new(&buf[..len]).read_to_string(&mut json).unwrap();
+        assert_eq!(json, sink.format(&record));
+    }
+
+    #[test]
+    fn tcp_null_delimited() {
+        let server=TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink=DGelf::tcp(server.local_addr().unwrap()).unwrap();
+        let (mut conn, _)=server.accept().unwrap();
+        sink.write(&DRecord::new(Level::Info, "one"), "").unwrap();
+        sink.write(&DRecord::new(Level::Info, "two"), "").unwrap();
+        drop(sink);
+
+        let mut received=Vec::new();
+        conn.read_to_end(&mut received).unwrap();
+        let messages: Vec<&[u8]>=received.split(|b| *b == 0).filter(|m| !m.is_empty()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(String::from_utf8_lossy(messages[1]).contains("\"short_message\":\"two\""));
+    }
+}
@@ -0,0 +1,622 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, RecvTimeoutError, Sender, SyncSender, TrySendError}, Arc, OnceLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::{app_name, json_str, record_json, DSink};
+
+/// Default max number of records in a batch.
+const DEFAULT_BATCH_SIZE: usize = 100;
+/// Default max time a record waits in a batch.
+const DEFAULT_BATCH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default number of retries for a failed batch.
+const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Default delay before first retry (doubles at each retry).
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+/// Default connect, read and write timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default max number of records waiting for the worker thread.
+const DEFAULT_QUEUE_LIMIT: usize = 10000;
+/// Default max total size of spilled batches, in bytes.
+const DEFAULT_SPILL_LIMIT: u64 = 64 * 1024 * 1024;
+/// Default max time [`DHttp`] flush (and drop) waits for the worker thread.
+const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Extension of spilled batch files.
+const SPILL_EXTENSION: &str = "batch";
+
+/// Body format of [`DHttp`] requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DHttpFormat {
+    /// Json array of records (default).
+    JsonArray,
+    /// One json record per line.
+    Ndjson,
+    /// Elasticsearch `_bulk` api: `{"index":{}}` line before each record.
+    EsBulk,
+    /// Loki push api: one stream labelled with `job`, values are formatted log lines.
+    Loki,
+}
+
+/// Settings of [`DHttp`], moved in worker thread when it starts.
+#[derive(Debug, Clone)]
+struct DHttpConfig {
+    /// Host (and port) for Host header
+    host: String,
+    /// Address to connect to
+    addr: String,
+    /// Request path
+    path: String,
+    /// Additional headers
+    headers: Vec<(String, String)>,
+    /// Body format
+    format: DHttpFormat,
+    /// Loki job label
+    job: String,
+    /// Max records in a batch
+    batch_size: usize,
+    /// Max time a record waits in a batch
+    batch_timeout: Duration,
+    /// Retries for a failed batch
+    max_retries: u32,
+    /// Delay before first retry
+    retry_backoff: Duration,
+    /// Connect, read and write timeout
+    timeout: Duration,
+    /// Max records waiting for the worker thread
+    queue_limit: usize,
+    /// Directory where failed batches are saved
+    spill_dir: Option<PathBuf>,
+    /// Max total size of spilled batches
+    spill_limit: u64,
+    /// Max time flush waits for the worker thread
+    flush_timeout: Duration,
+}
+
+/// Message for worker thread.
+#[derive(Debug)]
+enum DHttpMsg {
+    /// A serialized record
+    Record(String),
+    /// Send current batch now, then acknowledge
+    Flush(Sender<()>),
+}
+
+/// Sink that batches records and POSTs them to an http endpoint (e.g. a Loki push api or an
+/// Elasticsearch `_bulk` compatible endpoint).
+/// 
+/// A batch is sent when it reaches the batch size or when its first record is older than the batch
+/// timeout. Failed requests are retried with exponential backoff; if all retries fail the batch is
+/// saved in the spill directory (if set) and sent again when the endpoint is back.
+/// 
+/// Records are dropped, and counted in [`DHttp::dropped()`], when the queue to the background thread is
+/// full, or when a batch fails and the spill directory is not set or has reached its size limit.
+/// When the queue is full, [`DLog`](crate::DLog) writes the line in its own log file instead.
+/// 
+/// Requests are sent by a background thread. Only plain `http://` urls are supported.
+/// 
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DLog, DHttp, DHttpFormat};
+/// 
+/// let durylog=DLog::new()
+///     .with_sink(
+///         DHttp::new("http://localhost:3100/loki/api/v1/push").unwrap()
+///             .with_format(DHttpFormat::Loki)
+///             .with_spill_dir("log-spill")
+///     );
+/// ```
+#[derive(Debug)]
+pub struct DHttp {
+    /// Settings
+    config: DHttpConfig,
+    /// Channel to worker thread (started on first record)
+    sender: OnceLock<SyncSender<DHttpMsg>>,
+    /// Number of dropped records, shared with worker thread
+    dropped: Arc<AtomicU64>,
+    /// Worker thread
+    worker: OnceLock<JoinHandle<()>>,
+}
+
+impl DHttp {
+    /// Create a sink that posts to ['url'] (`http://host[:port]/path`).
+    pub fn new(url: &str) -> Result<Self, DLogError> {
+        let rest=url.strip_prefix("http://")
+            .ok_or_else(|| DLogError::Err(io::Error::new(io::ErrorKind::InvalidInput, "only http:// urls are supported")))?;
+        let (host, path)=match rest.find('/') {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(DLogError::Err(io::Error::new(io::ErrorKind::InvalidInput, "missing host in url")));
+        }
+        let addr=if host.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+
+        Ok(Self {
+            config: DHttpConfig {
+                host: host.to_string(),
+                addr,
+                path: path.to_string(),
+                headers: Vec::new(),
+                format: DHttpFormat::JsonArray,
+                job: app_name(),
+                batch_size: DEFAULT_BATCH_SIZE,
+                batch_timeout: DEFAULT_BATCH_TIMEOUT,
+                max_retries: DEFAULT_MAX_RETRIES,
+                retry_backoff: DEFAULT_RETRY_BACKOFF,
+                timeout: DEFAULT_TIMEOUT,
+                queue_limit: DEFAULT_QUEUE_LIMIT,
+                spill_dir: None,
+                spill_limit: DEFAULT_SPILL_LIMIT,
+                flush_timeout: DEFAULT_FLUSH_TIMEOUT,
+            },
+            sender: OnceLock::new(),
+            dropped: Arc::new(AtomicU64::new(0)),
+            worker: OnceLock::new(),
+        })
+    }
+
+    /// Set body format. Default is [`DHttpFormat::JsonArray`].
+    pub fn with_format(mut self, format: DHttpFormat) -> Self {
+        self.config.format=format;
+        self
+    }
+
+    /// Add an header to every request (e.g. "Authorization").
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.config.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set Loki `job` label. Default is current executable name.
+    pub fn with_job(mut self, job: &str) -> Self {
+        self.config.job=job.to_string();
+        self
+    }
+
+    /// Set max records in a batch and max time a record waits before the batch is sent.
+    pub fn with_batch(mut self, size: usize, timeout: Duration) -> Self {
+        self.config.batch_size=size.max(1);
+        self.config.batch_timeout=timeout;
+        self
+    }
+
+    /// Set number of retries of a failed batch and delay before first retry (doubles at each retry).
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.config.max_retries=max_retries;
+        self.config.retry_backoff=backoff;
+        self
+    }
+
+    /// Set connect, read and write timeout. Default is 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout=timeout;
+        self
+    }
+
+    /// Set max number of records waiting for the background thread. Default is 10000.
+    pub fn with_queue_limit(mut self, limit: usize) -> Self {
+        self.config.queue_limit=limit.max(1);
+        self
+    }
+
+    /// Save batches that cannot be sent in ['dir'] (created if missing), to send them later.
+    pub fn with_spill_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.config.spill_dir=Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set max total size of spilled batches, in bytes. Default is 64 MB.
+    pub fn with_spill_limit(mut self, bytes: u64) -> Self {
+        self.config.spill_limit=bytes;
+        self
+    }
+
+    /// Set max time flush (and drop) waits for pending batches to be sent. Default is 5 seconds.
+    /// Batches still pending after it are sent by the background thread as long as the process runs.
+    pub fn with_flush_timeout(mut self, timeout: Duration) -> Self {
+        self.config.flush_timeout=timeout;
+        self
+    }
+
+    /// ['return'] number of records dropped so far (queue full, or batch neither sent nor spilled).
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// ['return'] channel to worker thread, starting it if needed.
+    fn sender(&self) -> &SyncSender<DHttpMsg> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver)=mpsc::sync_channel(self.config.queue_limit);
+            let config=self.config.clone();
+            let dropped=Arc::clone(&self.dropped);
+            let spawned=thread::Builder::new()
+                .name(String::from("dlog-http"))
+                .spawn(move || worker(config, receiver, dropped));
+            if let Ok(handle) = spawned {
+                self.worker.set(handle).ok();
+            }
+            sender
+        })
+    }
+
+    /// Ask worker thread to send current batch and wait at most flush timeout.
+    /// ['return'] true if worker is not started or has sent the batch in time.
+    fn wait_flush(&self) -> bool {
+        let Some(sender) = self.sender.get() else { return true };
+        let (ack, done)=mpsc::channel();
+        match sender.try_send(DHttpMsg::Flush(ack)) {
+            Ok(()) => done.recv_timeout(self.config.flush_timeout).is_ok(),
+            // Queue full: worker is busy for longer than any reasonable wait
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Disconnected(_)) => true,
+        }
+    }
+}
+
+impl DSink for DHttp {
+    fn write(&self, record: &DRecord, line: &str) -> Result<(), DLogError> {
+        let item=match self.config.format {
+            DHttpFormat::Loki => {
+                let nanos=record.timestamp.timestamp_nanos_opt().unwrap_or_default();
+                format!("[\"{}\",{}]", nanos, json_str(line))
+            },
+            _ => record_json(record),
+        };
+        match self.sender().try_send(DHttpMsg::Record(item)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Err(DLogError::Overflow)
+            },
+            Err(TrySendError::Disconnected(_)) => Err(DLogError::Err(io::Error::new(io::ErrorKind::BrokenPipe, "http worker stopped"))),
+        }
+    }
+
+    fn flush(&self) {
+        self.wait_flush();
+    }
+}
+
+impl Drop for DHttp {
+    fn drop(&mut self) {
+        // Closing the channel makes worker exit; join it only if it is idle, to never block longer
+        // than flush timeout (e.g. from a panic hook)
+        let idle=self.wait_flush();
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if idle {
+                worker.join().ok();
+            }
+        }
+    }
+}
+
+/// Worker thread: collects records in batches and sends them.
+fn worker(config: DHttpConfig, receiver: mpsc::Receiver<DHttpMsg>, dropped: Arc<AtomicU64>) {
+    let mut batch: Vec<String>=Vec::new();
+    let mut batch_start=Instant::now();
+    loop {
+        let msg=if batch.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(config.batch_timeout.saturating_sub(batch_start.elapsed()))
+        };
+
+        match msg {
+            Ok(DHttpMsg::Record(item)) => {
+                if batch.is_empty() {
+                    batch_start=Instant::now();
+                }
+                batch.push(item);
+                if batch.len() >= config.batch_size {
+                    send_batch(&config, &mut batch, &dropped);
+                }
+            },
+            Ok(DHttpMsg::Flush(ack)) => {
+                send_batch(&config, &mut batch, &dropped);
+                ack.send(()).ok();
+            },
+            Err(RecvTimeoutError::Timeout) => send_batch(&config, &mut batch, &dropped),
+            Err(RecvTimeoutError::Disconnected) => {
+                if !batch.is_empty() {
+                    send_batch(&config, &mut batch, &dropped);
+                }
+                break;
+            },
+        }
+    }
+}
+
+/// Send spilled batches and then ['batch'] (emptied). If sending fails, the batch is spilled,
+/// or counted in ['dropped'] if it cannot be.
+fn send_batch(config: &DHttpConfig, batch: &mut Vec<String>, dropped: &AtomicU64) {
+    let body=if batch.is_empty() { None } else { Some(build_body(config, batch)) };
+    let count=batch.len() as u64;
+    batch.clear();
+
+    // Send previously spilled batches first, to keep order
+    let mut online=true;
+    if let Some(dir) = &config.spill_dir {
+        for file in spilled_files(dir) {
+            match fs::read(&file) {
+                Ok(spilled) if post_with_retries(config, &spilled) => {
+                    fs::remove_file(&file).ok();
+                },
+                Ok(_) => {
+                    online=false;
+                    break;
+                },
+                Err(_) => {},
+            }
+        }
+    }
+
+    if let Some(body) = body {
+        if (!online || !post_with_retries(config, body.as_bytes())) && !spill(config, body.as_bytes()) {
+            dropped.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+}
+
+/// ['return'] request body for ['batch'] items.
+fn build_body(config: &DHttpConfig, batch: &[String]) -> String {
+    match config.format {
+        DHttpFormat::JsonArray => String::from("[") + &batch.join(",") + "]",
+        DHttpFormat::Ndjson => batch.iter().map(|item| item.clone() + "\n").collect(),
+        DHttpFormat::EsBulk => batch.iter().map(|item| String::from("{\"index\":{}}\n") + item + "\n").collect(),
+        DHttpFormat::Loki => format!("{{\"streams\":[{{\"stream\":{{\"job\":{}}},\"values\":[{}]}}]}}", json_str(&config.job), batch.join(",")),
+    }
+}
+
+/// ['return'] content type of ['format'].
+fn content_type(format: DHttpFormat) -> &'static str {
+    match format {
+        DHttpFormat::JsonArray | DHttpFormat::Loki => "application/json",
+        DHttpFormat::Ndjson | DHttpFormat::EsBulk => "application/x-ndjson",
+    }
+}
+
+/// Post ['body'], retrying with backoff. ['return'] true on success.
+fn post_with_retries(config: &DHttpConfig, body: &[u8]) -> bool {
+    let mut backoff=config.retry_backoff;
+    for attempt in 0..=config.max_retries {
+        if attempt > 0 {
+            thread::sleep(backoff);
+            backoff*=2;
+        }
+        if post(config, body).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Post ['body'] to endpoint. Success if response status is 2xx.
+fn post(config: &DHttpConfig, body: &[u8]) -> io::Result<()> {
+    let addr=config.addr.to_socket_addrs()?.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+    let mut stream=TcpStream::connect_timeout(&addr, config.timeout)?;
+    stream.set_read_timeout(Some(config.timeout))?;
+    stream.set_write_timeout(Some(config.timeout))?;
+
+    let mut request=format!("POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        config.path, config.host, content_type(config.format), body.len());
+    for (name, value) in &config.headers {
+        request+=&format!("{}: {}\r\n", name, value);
+    }
+    request+="\r\n";
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(body)?;
+
+    let mut status_line=String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    let status=status_line.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok()).unwrap_or_default();
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("http status {}", status)))
+    }
+}
+
+/// Save ['body'] in spill directory, if set and not over its size limit. ['return'] true if saved.
+fn spill(config: &DHttpConfig, body: &[u8]) -> bool {
+    let Some(dir) = &config.spill_dir else { return false };
+    let spilled: u64=spilled_files(dir).iter().filter_map(|file| fs::metadata(file).ok()).map(|m| m.len()).sum();
+    if spilled + body.len() as u64 > config.spill_limit {
+        return false;
+    }
+    let nanos=SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    fs::create_dir_all(dir).ok();
+    fs::write(dir.join(format!("{:024}.{}", nanos, SPILL_EXTENSION)), body).is_ok()
+}
+
+/// ['return'] spilled batch files in ['dir'], oldest first.
+fn spilled_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf>=fs::read_dir(dir).map(|entries| {
+        entries.filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == SPILL_EXTENSION))
+            .collect()
+    }).unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::{io::Read, net::TcpListener, sync::mpsc::Receiver};
+
+    /// Start a stand-in http server answering with ['statuses'], one per request.
+    /// ['return'] its url and a channel receiving request bodies.
+    fn server(statuses: Vec<u16>) -> (String, Receiver<String>) {
+        let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+        let url=format!("http://{}/push", listener.local_addr().unwrap());
+        let (sender, receiver)=mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _)=listener.accept().unwrap();
+                let mut reader=BufReader::new(stream);
+                let mut length=0;
+                loop {
+                    let mut line=String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length=value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body=vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                write!(reader.get_mut(), "HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+                if status == 200 {
+                    sender.send(String::from_utf8(body).unwrap()).unwrap();
+                }
+            }
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn batch_by_size() {
+        let (url, bodies)=server(vec![200]);
+        let sink=DHttp::new(&url).unwrap()
+            .with_format(DHttpFormat::Ndjson)
+            .with_batch(2, Duration::from_secs(60));
+        sink.write(&DRecord::new(Level::Info, "one"), "").unwrap();
+        sink.write(&DRecord::new(Level::Warn, "two"), "").unwrap();
+
+        let body=bodies.recv_timeout(Duration::from_secs(5)).unwrap();
+        let lines: Vec<&str>=body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"level\":\"INFO\"") && lines[0].ends_with("\"message\":\"one\"}"), "{}", lines[0]);
+        assert!(lines[1].ends_with("\"message\":\"two\"}"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn batch_by_time() {
+        let (url, bodies)=server(vec![200]);
+        let sink=DHttp::new(&url).unwrap().with_batch(100, Duration::from_millis(50));
+        sink.write(&DRecord::new(Level::Info, "alone"), "").unwrap();
+
+        let body=bodies.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(body.starts_with("[{") && body.ends_with("\"message\":\"alone\"}]"), "{}", body);
+    }
+
+    #[test]
+    fn spill_and_resend() {
+        let dir=std::env::temp_dir().join(format!("durylog-http-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        // First batch fails twice (1 retry), then everything works
+        let (url, bodies)=server(vec![500, 503, 200, 200]);
+        let sink=DHttp::new(&url).unwrap()
+            .with_format(DHttpFormat::Loki)
+            .with_job("test")
+            .with_retries(1, Duration::ZERO)
+            .with_spill_dir(&dir);
+
+        sink.write(&DRecord::new(Level::Info, ""), "first line").unwrap();
+        sink.flush();
+        assert_eq!(spilled_files(&dir).len(), 1);
+
+        sink.write(&DRecord::new(Level::Info, ""), "second line").unwrap();
+        sink.flush();
+        assert!(spilled_files(&dir).is_empty());
+
+        let first=bodies.recv_timeout(Duration::from_secs(5)).unwrap();
+        let second=bodies.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(first.starts_with("{\"streams\":[{\"stream\":{\"job\":\"test\"},\"values\":[[\""), "{}", first);
+        assert!(first.ends_with(",\"first line\"]]}]}"), "{}", first);
+        assert!(second.ends_with(",\"second line\"]]}]}"), "{}", second);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn drop_without_spill_dir_or_over_spill_limit() {
+        let dir=std::env::temp_dir().join(format!("durylog-http-limit-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let (url, _bodies)=server(vec![500, 500]);
+        let sink=DHttp::new(&url).unwrap().with_retries(0, Duration::ZERO);
+        sink.write(&DRecord::new(Level::Info, "lost"), "").unwrap();
+        sink.flush();
+        assert_eq!(sink.dropped(), 1);
+
+        let sink=DHttp::new(&url).unwrap().with_retries(0, Duration::ZERO).with_spill_dir(&dir).with_spill_limit(10);
+        sink.write(&DRecord::new(Level::Info, "too big"), "").unwrap();
+        sink.flush();
+        assert_eq!(sink.dropped(), 1);
+        assert!(spilled_files(&dir).is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn full_queue_drops() {
+        // Endpoint that accepts and never answers: worker waits for the timeout on first record
+        let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+        let url=format!("http://{}/push", listener.local_addr().unwrap());
+        let (accepted, busy)=mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _)=listener.accept().unwrap();
+            accepted.send(()).unwrap();
+            thread::sleep(Duration::from_secs(2));
+            drop(stream);
+        });
+        let sink=DHttp::new(&url).unwrap()
+            .with_batch(1, Duration::from_secs(60))
+            .with_retries(0, Duration::ZERO)
+            .with_timeout(Duration::from_secs(1))
+            .with_queue_limit(1);
+
+        sink.write(&DRecord::new(Level::Info, "sending"), "").unwrap();
+        busy.recv_timeout(Duration::from_secs(5)).unwrap();
+        sink.write(&DRecord::new(Level::Info, "queued"), "").unwrap();
+        assert!(matches!(sink.write(&DRecord::new(Level::Info, "dropped"), ""), Err(DLogError::Overflow)));
+        assert_eq!(sink.dropped(), 1);
+    }
+
+    #[test]
+    fn bounded_flush() {
+        // Endpoint that accepts and never answers
+        let listener=TcpListener::bind("127.0.0.1:0").unwrap();
+        let url=format!("http://{}/push", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _streams: Vec<_>=listener.incoming().collect();
+        });
+        let sink=DHttp::new(&url).unwrap()
+            .with_retries(0, Duration::ZERO)
+            .with_timeout(Duration::from_secs(10))
+            .with_flush_timeout(Duration::from_millis(100));
+
+        sink.write(&DRecord::new(Level::Info, "stuck"), "").unwrap();
+        let start=Instant::now();
+        sink.flush();
+        drop(sink);
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn named_worker() {
+        let (url, _bodies)=server(vec![]);
+        let sink=DHttp::new(&url).unwrap();
+        sink.write(&DRecord::new(Level::Info, "start"), "").unwrap();
+        assert_eq!(sink.worker.get().unwrap().thread().name(), Some("dlog-http"));
+    }
+
+    #[test]
+    fn only_http_urls() {
+        assert!(DHttp::new("https://example.com").is_err());
+        assert_eq!(DHttp::new("http://example.com").unwrap().config.addr, "example.com:80");
+    }
+}
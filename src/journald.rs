@@ -0,0 +1,254 @@
+use std::{os::unix::net::UnixDatagram, path::Path};
+#[cfg(target_os = "linux")]
+use std::{fs::File, io::{self, Write}, mem, os::fd::{AsRawFd, FromRawFd, RawFd}, ptr};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::{app_name, DSink};
+use crate::syslog::severity;
+
+/// Default journald native protocol socket.
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+/// Fields written by [`DJournald`] itself: key-values with these names get [`USER_FIELD_PREFIX`].
+const OWN_FIELDS: [&str; 6] = ["MESSAGE", "PRIORITY", "SYSLOG_IDENTIFIER", "TARGET", "CODE_FILE", "CODE_LINE"];
+/// Prefix of key-values named as a field written by [`DJournald`].
+const USER_FIELD_PREFIX: &str = "FIELD_";
+
+/// Sink that sends records to systemd-journald using its native protocol, as structured fields:
+/// `MESSAGE`, `PRIORITY`, `SYSLOG_IDENTIFIER`, `TARGET`, `CODE_FILE`, `CODE_LINE` and one field for
+/// each key-value (name in uppercase, invalid chars replaced by '_', prefixed by `FIELD_` if it is
+/// one of the previous names).
+/// 
+/// On Linux, records too big for a datagram are sent through a sealed memfd, as journald expects.
+/// 
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DLog, DJournald};
+/// 
+/// let durylog=DLog::new()
+///     .with_sink(DJournald::new().unwrap().with_identifier("my_app"));
+/// ```
+#[derive(Debug)]
+pub struct DJournald {
+    /// Socket connected to journald
+    socket: UnixDatagram,
+    /// SYSLOG_IDENTIFIER field
+    identifier: String,
+}
+
+impl DJournald {
+    /// Create a sink connected to [`JOURNALD_SOCKET`].
+    pub fn new() -> Result<Self, DLogError> {
+        Self::with_socket(JOURNALD_SOCKET)
+    }
+
+    /// Create a sink connected to journald socket ['path'].
+    pub fn with_socket<P: AsRef<Path>>(path: P) -> Result<Self, DLogError> {
+        let socket=UnixDatagram::unbound().map_err(DLogError::Err)?;
+        socket.connect(path).map_err(DLogError::Err)?;
+        Ok(Self {
+            socket,
+            identifier: app_name(),
+        })
+    }
+
+    /// Set SYSLOG_IDENTIFIER field. Default is current executable name.
+    pub fn with_identifier(mut self, identifier: &str) -> Self {
+        self.identifier=identifier.to_string();
+        self
+    }
+
+    /// ['return'] ['record'] serialized as journald native protocol datagram.
+    fn serialize(&self, record: &DRecord) -> Vec<u8> {
+        let mut buf=Vec::new();
+        add_field(&mut buf, "MESSAGE", &record.message);
+        add_field(&mut buf, "PRIORITY", &severity(record.level).to_string());
+        add_field(&mut buf, "SYSLOG_IDENTIFIER", &self.identifier);
+        if !record.target.is_empty() {
+            add_field(&mut buf, "TARGET", &record.target);
+        }
+        if let Some(file) = &record.file {
+            add_field(&mut buf, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line {
+            add_field(&mut buf, "CODE_LINE", &line.to_string());
+        }
+        for (key, value) in &record.fields {
+            if let Some(name) = field_name(key) {
+                add_field(&mut buf, &name, value);
+            }
+        }
+        buf
+    }
+}
+
+impl DSink for DJournald {
+    fn write(&self, record: &DRecord, _line: &str) -> Result<(), DLogError> {
+        let data=self.serialize(record);
+        match self.socket.send(&data) {
+            Ok(_) => Ok(()),
+            #[cfg(target_os = "linux")]
+            Err(err) if matches!(err.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => {
+                send_memfd(&self.socket, &data).map_err(DLogError::Err)
+            },
+            Err(err) => Err(DLogError::Err(err)),
+        }
+    }
+}
+
+/// Send ['data'] to journald in a sealed memfd, for records over datagram size limit.
+#[cfg(target_os = "linux")]
+fn send_memfd(socket: &UnixDatagram, data: &[u8]) -> io::Result<()> {
+    // SAFETY: name is a valid nul terminated string
+    let fd=unsafe { libc::memfd_create(c"durylog-journald".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fd is a new descriptor, owned only by this File
+    let mut file=unsafe { File::from_raw_fd(fd) };
+    file.write_all(data)?;
+    // journald accepts only memfds that cannot change anymore
+    let seals=libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: fd is open for the lifetime of file
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    send_fd(socket, file.as_raw_fd())
+}
+
+/// Send ['fd'] on connected ['socket'] as SCM_RIGHTS ancillary data of an empty datagram.
+#[cfg(target_os = "linux")]
+fn send_fd(socket: &UnixDatagram, fd: RawFd) -> io::Result<()> {
+    let fd_size=mem::size_of::<RawFd>() as u32;
+    // SAFETY: CMSG_SPACE only computes a size
+    let space=unsafe { libc::CMSG_SPACE(fd_size) } as usize;
+    // u64 items keep control buffer aligned as cmsghdr
+    let mut control=vec![0u64; space.div_ceil(mem::size_of::<u64>())];
+    // SAFETY: msghdr is a plain C struct, all zeroes is a valid empty message
+    let mut msg: libc::msghdr=unsafe { mem::zeroed() };
+    msg.msg_control=control.as_mut_ptr().cast();
+    msg.msg_controllen=space as _;
+    // SAFETY: control is big enough for one cmsghdr carrying one fd, so CMSG_FIRSTHDR is not null
+    // and CMSG_DATA points inside control
+    unsafe {
+        let cmsg=libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level=libc::SOL_SOCKET;
+        (*cmsg).cmsg_type=libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len=libc::CMSG_LEN(fd_size) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+    }
+    // SAFETY: msg and control are valid for the call
+    if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Append field ['name'] = ['value'] to ['buf'], using binary format if value contains newlines.
+fn add_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+/// ['return'] a valid journald field name from ['key']: uppercase, only letters digits and '_',
+/// not starting with '_' or a digit, prefixed if it is a field written by the sink.
+/// None if nothing is left.
+fn field_name(key: &str) -> Option<String> {
+    let name: String=key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .skip_while(|c| *c == '_' || c.is_ascii_digit())
+        .take(64 - USER_FIELD_PREFIX.len())
+        .collect();
+    if name.is_empty() {
+        None
+    } else if OWN_FIELDS.contains(&name.as_str()) {
+        Some(String::from(USER_FIELD_PREFIX) + &name)
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn native_protocol() {
+        let path=std::env::temp_dir().join(format!("durylog-journald-{}.sock", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let server=UnixDatagram::bind(&path).unwrap();
+        let sink=DJournald::with_socket(&path).unwrap().with_identifier("test");
+
+        let mut record=DRecord::new(Level::Warn, "line1\nline2");
+        record.target="my_app::db".to_string();
+        record.file=Some("src/db.rs".to_string());
+        record.line=Some(42);
+        record.fields.push(("request-id".to_string(), "abc".to_string()));
+        sink.write(&record, "").unwrap();
+
+        let mut buf=[0; 1024];
+        let len=server.recv(&mut buf).unwrap();
+        let mut expected=b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&11u64.to_le_bytes());
+        expected.extend_from_slice(b"line1\nline2\n");
+        expected.extend_from_slice(b"PRIORITY=4\nSYSLOG_IDENTIFIER=test\nTARGET=my_app::db\nCODE_FILE=src/db.rs\nCODE_LINE=42\nREQUEST_ID=abc\n");
+        assert_eq!(&buf[..len], &expected[..]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("user.id").as_deref(), Some("USER_ID"));
+        assert_eq!(field_name("_1x").as_deref(), Some("X"));
+        assert_eq!(field_name("__"), None);
+        assert_eq!(field_name("message").as_deref(), Some("FIELD_MESSAGE"));
+        assert_eq!(field_name("_priority").as_deref(), Some("FIELD_PRIORITY"));
+        assert_eq!(field_name(&"x".repeat(100)).unwrap().len(), 58);
+    }
+
+    /// ['return'] content of the memfd received on ['server'].
+    #[cfg(target_os = "linux")]
+    fn recv_memfd(server: &UnixDatagram) -> Vec<u8> {
+        use std::io::{Read, Seek, SeekFrom};
+        let mut control=[0u64; 8];
+        let mut msg: libc::msghdr=unsafe { mem::zeroed() };
+        msg.msg_control=control.as_mut_ptr().cast();
+        msg.msg_controllen=mem::size_of_val(&control) as _;
+        assert_eq!(unsafe { libc::recvmsg(server.as_raw_fd(), &mut msg, 0) }, 0);
+        let fd=unsafe {
+            let cmsg=libc::CMSG_FIRSTHDR(&msg);
+            assert_eq!((*cmsg).cmsg_type, libc::SCM_RIGHTS);
+            ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>())
+        };
+        let mut file=unsafe { File::from_raw_fd(fd) };
+        assert!(file.write_all(b"x").is_err(), "memfd is not sealed");
+        let mut data=Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn big_record_in_memfd() {
+        let path=std::env::temp_dir().join(format!("durylog-journald-big-{}.sock", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let server=UnixDatagram::bind(&path).unwrap();
+        let sink=DJournald::with_socket(&path).unwrap().with_identifier("test");
+
+        let message="x".repeat(4 * 1024 * 1024);
+        sink.write(&DRecord::new(Level::Info, &message), "").unwrap();
+
+        let data=recv_memfd(&server);
+        assert_eq!(data.len(), "MESSAGE=".len() + message.len() + "\nPRIORITY=6\nSYSLOG_IDENTIFIER=test\n".len());
+        assert!(data.starts_with(b"MESSAGE=xxx") && data.ends_with(b"\nPRIORITY=6\nSYSLOG_IDENTIFIER=test\n"));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,202 @@
+use log::{Level, Log, Record};
+use std::fmt;
+use tracing_core::{field::{Field, Visit}, span, Event, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use crate::DLog;
+
+/// tracing-subscriber [`Layer`] that writes tracing events through a [`DLog`] (or the std::log logger), so
+/// they get the same format, colors, filters and outputs of std::log records.
+/// 
+/// Event fields become record fields; fields of the enclosing spans are added before them, and the span
+/// path (e.g. `request:db`) is added as field `span`.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{DLog, DTracingLayer};
+/// use tracing_subscriber::prelude::*;
+/// 
+/// DLog::new().init_logger().ok();
+/// tracing_subscriber::registry().with(DTracingLayer::new()).init();
+/// ```
+pub struct DTracingLayer {
+    /// Logger to write to, std::log logger if None
+    logger: Option<DLog>,
+}
+
+impl fmt::Debug for DTracingLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DTracingLayer").field("global", &self.logger.is_none()).finish()
+    }
+}
+
+impl Default for DTracingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fields of a span, stored in span extensions.
+struct DSpanFields(Vec<(String, String)>);
+
+/// Visitor that collects tracing fields as strings, apart the `message` one.
+#[derive(Default)]
+struct DFieldsVisitor {
+    /// Event message
+    message: String,
+    /// Other fields
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for DFieldsVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message=value.to_string();
+        } else {
+            self.fields.push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message=format!("{:?}", value);
+        } else {
+            self.fields.push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+}
+
+impl DTracingLayer {
+    /// Create a layer that writes through the std::log logger (set it with [`DLog::init_logger()`]).
+    pub fn new() -> Self {
+        Self { logger: None }
+    }
+
+    /// Create a layer that writes through ['logger'].
+    pub fn with_logger(logger: DLog) -> Self {
+        Self { logger: Some(logger) }
+    }
+
+    /// ['return'] logger to write to.
+    fn logger(&self) -> &dyn Log {
+        match &self.logger {
+            Some(logger) => logger,
+            None => log::logger(),
+        }
+    }
+}
+
+/// ['return'] std::log level for tracing ['level'].
+fn log_level(level: &tracing_core::Level) -> Level {
+    match *level {
+        tracing_core::Level::ERROR => Level::Error,
+        tracing_core::Level::WARN => Level::Warn,
+        tracing_core::Level::INFO => Level::Info,
+        tracing_core::Level::DEBUG => Level::Debug,
+        tracing_core::Level::TRACE => Level::Trace,
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for DTracingLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span)=ctx.span(id) {
+            let mut visitor=DFieldsVisitor::default();
+            attrs.record(&mut visitor);
+            span.extensions_mut().insert(DSpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span)=ctx.span(id) {
+            let mut visitor=DFieldsVisitor::default();
+            values.record(&mut visitor);
+            if let Some(fields)=span.extensions_mut().get_mut::<DSpanFields>() {
+                fields.0.extend(visitor.fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata=event.metadata();
+        let level=log_level(metadata.level());
+        let logger=self.logger();
+        // DLog is also enabled for records kept by its flight recorder: filters are applied by its log()
+        if !logger.enabled(&log::Metadata::builder().level(level).target(metadata.target()).build()) {
+            return;
+        }
+
+        let mut fields=Vec::new();
+        let mut path=Vec::new();
+        if let Some(scope)=ctx.event_scope(event) {
+            for span in scope.from_root() {
+                path.push(span.name());
+                if let Some(span_fields)=span.extensions().get::<DSpanFields>() {
+                    fields.extend(span_fields.0.iter().cloned());
+                }
+            }
+        }
+        let mut visitor=DFieldsVisitor::default();
+        event.record(&mut visitor);
+        fields.extend(visitor.fields);
+        if !path.is_empty() {
+            fields.push(("span".to_string(), path.join(":")));
+        }
+
+        logger.log(&Record::builder()
+            .level(level)
+            .target(metadata.target())
+            .file(metadata.file())
+            .line(metadata.line())
+            .module_path(metadata.module_path())
+            .key_values(&fields)
+            .args(format_args!("{}", visitor.message))
+            .build());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DMemory, LevelFilter};
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn events_and_spans_are_logged() {
+        let memory=DMemory::new(10);
+        let mut logger=DLog::new().without_console().with_sink(memory.clone());
+        logger.with_level(LevelFilter::Info);
+        let subscriber=tracing_subscriber::registry().with(DTracingLayer::with_logger(logger));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span=tracing::info_span!("request", id=7);
+            let _enter=span.enter();
+            tracing::info!(user="bob", "hello {}", "world");
+            tracing::debug!("filtered out");
+        });
+
+        let records=memory.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, Level::Info);
+        assert_eq!(records[0].message, "hello world");
+        assert_eq!(records[0].file.as_deref(), Some(file!()));
+        let fields: Vec<(&str, &str)>=records[0].fields.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(fields, [("id", "7"), ("user", "bob"), ("span", "request")]);
+    }
+
+    #[test]
+    fn filtered_events_reach_flight_recorder() {
+        let memory=DMemory::new(10);
+        let mut logger=DLog::new().without_console().with_sink(memory.clone()).with_flight_recorder(5);
+        logger.with_level(LevelFilter::Info);
+        let subscriber=tracing_subscriber::registry().with(DTracingLayer::with_logger(logger));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(attempt=1, "connecting");
+            tracing::error!("connection failed");
+        });
+
+        let records=memory.records();
+        assert_eq!(memory.messages()[1], "connecting");
+        assert_eq!(records[1].fields, [("attempt".to_string(), "1".to_string())]);
+        assert_eq!(records.last().unwrap().message, "connection failed");
+    }
+}
@@ -51,7 +51,7 @@
 //! This will log on stdout without colors.
 //! 
 //! ### Directly usage with custom settings:
-//! ```rust
+//! ```rust,no_run
 //! use durylog::DLog;
 //! 
 //! fn main() {
@@ -89,7 +89,7 @@
 //! This will log on stdout without colors.
 //! 
 //! ### Macros usage with custom settings:
-//! ```rust
+//! ```rust,no_run
 //! use durylog::{error,warn,info,debug,trace,DLog};
 //! 
 //! fn main() {
@@ -112,16 +112,92 @@
 
 #[cfg(test)]
 mod tests {
+    use crate::{DLog, Level, assert_logged, error, info};
+    use std::{cell::Cell, fmt};
+
+    /// Test helper that counts how many times it is formatted.
+    pub(crate) struct Counted<'a>(pub(crate) &'a Cell<u32>);
+
+    impl fmt::Display for Counted<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.set(self.0.get() + 1);
+            write!(f, "counted")
+        }
+    }
+
     #[test]
     fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+        let logs=crate::capture();
+        error!("Error message");
+        info!(user = "bob"; "Info message");
+        DLog::new().without_console().with_sink(crate::capture_sink()).d("Debug message");
+
+        assert_logged!(Level::Error, "Error message");
+        assert_logged!(Level::Info, "Info message");
+        assert_logged!(Level::Debug, "Debug message");
+        assert_eq!(logs.len(), 3);
     }
 }
 
-/// the only module in this crate.
+/// the main module in this crate.
 pub mod durylog;
-pub use crate::durylog::DLog;
+/// log files: rotation and routing.
+pub mod file;
+/// secrets redaction: hides secrets in messages and key-value fields before they are formatted.
+pub mod redact;
+/// rate limiting and sampling.
+pub mod limit;
+/// clock of time based settings, that tests can move forward.
+mod clock;
+/// additional outputs.
+pub mod sink;
+/// syslog output.
+pub mod syslog;
+/// tcp/udp network output.
+pub mod network;
+/// GELF (Graylog) output.
+pub mod gelf;
+/// http batch output.
+pub mod http;
+/// in-memory output and log capture for tests.
+pub mod memory;
+/// callback and channel outputs.
+pub mod callback;
+/// thread-local diagnostic context.
+pub mod context;
+/// elapsed time logging.
+pub mod timer;
+/// tracing-subscriber integration.
+#[cfg(feature = "tracing")]
+pub mod layer;
+/// formatted-arguments macros for direct use.
+#[macro_use]
+mod macros;
+/// panic hook that logs panics.
+pub mod panic_hook;
+/// systemd-journald output.
+#[cfg(unix)]
+pub mod journald;
+pub use crate::durylog::{DLog, DScope, DRecord, DLogError};
+pub use crate::file::DFileRoute;
+pub use crate::redact::DRedactor;
+pub use crate::limit::DRateLimiter;
+pub use crate::sink::DSink;
+pub use crate::syslog::{DSyslog, DSyslogFacility, DSyslogFormat};
+pub use crate::network::{DNetwork, DNetProtocol, DOverflow};
+pub use crate::gelf::DGelf;
+pub use crate::http::{DHttp, DHttpFormat};
+pub use crate::memory::{DMemory, DCapture, capture, capture_sink};
+pub use crate::callback::{DCallback, DChannel};
+pub use crate::panic_hook::PANIC_TARGET;
+#[cfg(unix)]
+pub use crate::journald::DJournald;
+#[cfg(feature = "tracing")]
+pub use crate::layer::DTracingLayer;
+pub use crate::context::DContextGuard;
+pub use crate::timer::DTimer;
+pub use crate::durylog::{DFormat, DLevelAlign, DMultiline, DSanitize, LEVEL_LABELS_DEFAULT, LEVEL_LABELS_LOWER, LEVEL_LABELS_SHORT, LEVEL_LABELS_FULL};
+pub use crate::durylog::{Level, LevelFilter};
 pub use crate::durylog::debug;
 pub use crate::durylog::error;
 pub use crate::durylog::warn;
@@ -0,0 +1,233 @@
+use log::Level;
+use crate::clock;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Rate limits and sampling rules to prevent a chatty module from flooding the log.
+/// 
+/// Rate limits are token buckets: a key can write a burst of up to `lines_per_sec` lines, then
+/// it is refilled at `lines_per_sec`. Records exceeding the limit are dropped and counted, and every
+/// summary interval a `Warn` line reports how many records have been suppressed.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{DLog, DRateLimiter};
+/// use log::Level;
+/// use std::time::Duration;
+/// 
+/// let durylog=DLog::new()
+///     .with_rate_limiter(
+///         DRateLimiter::new()
+///             .with_target_limit("my_app::net", 10.0) // at most 10 lines per second from my_app::net
+///             .with_sampling(Level::Trace, 0.01) // keep 1% of trace records
+///             .with_summary(Duration::from_secs(60))
+///     );
+/// ```
+#[derive(Debug)]
+pub struct DRateLimiter {
+    /// Limits by target prefix (lines per second)
+    target_limits: Vec<(String, f64)>,
+    /// Limit for each call site not covered by target limits (lines per second, 0 = no limit)
+    call_site_limit: f64,
+    /// Probability to keep a record, by level (Error..Trace)
+    sampling: [f64; 5],
+    /// Interval between suppressed records summaries (zero = on flush only)
+    summary_interval: Duration,
+    /// Buckets and counters
+    state: Mutex<DLimiterState>,
+}
+
+/// Mutable state of [`DRateLimiter`].
+#[derive(Debug)]
+struct DLimiterState {
+    /// Token buckets by key
+    buckets: HashMap<String, DBucket>,
+    /// Suppressed records count by key
+    suppressed: HashMap<String, u64>,
+    /// Last summary time
+    last_summary: Instant,
+    /// Sampling pseudo random generator state
+    seed: u64,
+}
+
+/// A token bucket.
+#[derive(Debug)]
+struct DBucket {
+    /// Available tokens
+    tokens: f64,
+    /// Last refill time
+    last: Instant,
+}
+
+impl Default for DRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DRateLimiter {
+    /// Create a limiter with no limits and no sampling.
+    pub fn new() -> Self {
+        let seed=SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default();
+        Self {
+            target_limits: Vec::new(),
+            call_site_limit: 0.0,
+            sampling: [1.0; 5],
+            summary_interval: Duration::ZERO,
+            state: Mutex::new(DLimiterState {
+                buckets: HashMap::new(),
+                suppressed: HashMap::new(),
+                last_summary: clock::now(),
+                seed: seed | 1,
+            }),
+        }
+    }
+
+    /// Limit records whose target starts with ['target'] to ['lines_per_sec'] (all matching targets share it).
+    pub fn with_target_limit<S: AsRef<str>>(mut self, target: S, lines_per_sec: f64) -> Self {
+        self.target_limits.push((target.as_ref().replace('-', "_"), lines_per_sec));
+        self
+    }
+
+    /// Limit each call site (source file and line) to ['lines_per_sec'].
+    pub fn with_call_site_limit(mut self, lines_per_sec: f64) -> Self {
+        self.call_site_limit=lines_per_sec;
+        self
+    }
+
+    /// Keep records of ['level'] with ['probability'] (0.0 to 1.0).
+    /// 
+    /// Intended for trace/debug levels.
+    pub fn with_sampling(mut self, level: Level, probability: f64) -> Self {
+        self.sampling[level as usize - 1]=probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Write a summary of suppressed records every ['interval'].
+    pub fn with_summary(mut self, interval: Duration) -> Self {
+        self.summary_interval=interval;
+        self
+    }
+
+    /// ['return'] interval between suppressed records summaries (zero = on flush only).
+    pub(crate) fn summary_interval(&self) -> Duration {
+        self.summary_interval
+    }
+
+    /// ['return'] true if a record of ['level'] and ['target'], logged at ['file']:['line'], can be written.
+    /// 
+    /// It is called before the record is formatted.
+    pub(crate) fn check(&self, level: Level, target: &str, file: Option<&str>, line: Option<u32>) -> bool {
+        let mut state=self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Sampling
+        let probability=self.sampling[level as usize - 1];
+        if probability < 1.0 && next_random(&mut state.seed) >= probability {
+            *state.suppressed.entry(format!("sampling {}", level)).or_default()+=1;
+            return false;
+        }
+
+        // Rate limit
+        let limit=self.target_limits.iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map(|(prefix, rate)| (prefix.clone(), *rate))
+            .or_else(|| {
+                (self.call_site_limit > 0.0).then(|| (call_site(target, file, line), self.call_site_limit))
+            });
+
+        if let Some((key, rate)) = limit {
+            let now=clock::now();
+            let bucket=state.buckets.entry(key.clone()).or_insert(DBucket { tokens: rate.max(1.0), last: now });
+            bucket.tokens=(bucket.tokens + now.duration_since(bucket.last).as_secs_f64() * rate).min(rate.max(1.0));
+            bucket.last=now;
+            if bucket.tokens < 1.0 {
+                *state.suppressed.entry(key).or_default()+=1;
+                return false;
+            }
+            bucket.tokens-=1.0;
+        }
+        true
+    }
+
+    /// ['return'] summary lines of suppressed records if summary interval has passed (or ['force']).
+    pub(crate) fn take_summary(&self, force: bool) -> Vec<String> {
+        let mut state=self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.suppressed.is_empty() {
+            return Vec::new();
+        }
+        if !force && (self.summary_interval.is_zero() || clock::now().duration_since(state.last_summary) < self.summary_interval) {
+            return Vec::new();
+        }
+
+        state.last_summary=clock::now();
+        let mut lines: Vec<String>=state.suppressed.drain()
+            // Empty key is a target limit for all targets
+            .map(|(key, count)| format!("suppressed {} records from {}", count, if key.is_empty() {"all targets"} else {&key}))
+            .collect();
+        lines.sort();
+        lines
+    }
+}
+
+/// ['return'] call site key: ['file'] and ['line'] when known, otherwise ['target'] (if any).
+fn call_site(target: &str, file: Option<&str>, line: Option<u32>) -> String {
+    match (file, line) {
+        (Some(file), Some(line)) => format!("{}:{}", file, line),
+        _ if target.is_empty() => String::from("unknown call site"),
+        _ => target.to_string(),
+    }
+}
+
+/// ['return'] a pseudo random number in 0.0..1.0 (xorshift64).
+fn next_random(seed: &mut u64) -> f64 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    (*seed >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_limit() {
+        let limiter=DRateLimiter::new().with_target_limit("my_app::net", 2.0);
+        assert!(limiter.check(Level::Info, "my_app::net::tcp", None, None));
+        assert!(limiter.check(Level::Info, "my_app::net::tcp", None, None));
+        assert!(!limiter.check(Level::Info, "my_app::net::tcp", None, None));
+
+        // Other targets are not limited
+        for _ in 0..3 {
+            assert!(limiter.check(Level::Info, "my_app::db", None, None));
+        }
+
+        assert_eq!(limiter.take_summary(true), vec!["suppressed 1 records from my_app::net"]);
+        assert!(limiter.take_summary(true).is_empty());
+    }
+
+    #[test]
+    fn summary_never_has_empty_keys() {
+        let limiter=DRateLimiter::new().with_call_site_limit(1.0);
+        assert!(limiter.check(Level::Info, "", Some("src/main.rs"), Some(3)));
+        assert!(!limiter.check(Level::Info, "", Some("src/main.rs"), Some(3)));
+        assert!(limiter.check(Level::Info, "", None, None));
+        assert!(!limiter.check(Level::Info, "", None, None));
+        assert_eq!(limiter.take_summary(true), ["suppressed 1 records from src/main.rs:3", "suppressed 1 records from unknown call site"]);
+
+        let limiter=DRateLimiter::new().with_target_limit("", 1.0);
+        assert!(limiter.check(Level::Info, "db", None, None));
+        assert!(!limiter.check(Level::Info, "net", None, None));
+        assert_eq!(limiter.take_summary(true), ["suppressed 1 records from all targets"]);
+    }
+
+    #[test]
+    fn sampling() {
+        let limiter=DRateLimiter::new().with_sampling(Level::Trace, 0.0);
+        assert!(!limiter.check(Level::Trace, "", None, None));
+        assert!(limiter.check(Level::Debug, "", None, None));
+    }
+}
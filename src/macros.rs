@@ -0,0 +1,106 @@
+/// Log a formatted message on a [`DLog`](crate::DLog) (or a [`DScope`](crate::DScope)) at a given level.
+/// 
+/// The message is formatted only if the level is enabled.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{dlog, DLog, Level};
+/// 
+/// let durylog=DLog::new();
+/// let x=42;
+/// dlog!(durylog, Level::Info, "x={}", x);
+/// ```
+#[macro_export]
+macro_rules! dlog {
+    ($logger:expr, $level:expr, $($arg:tt)+) => {
+        $logger.log_fmt($level, format_args!($($arg)+))
+    };
+}
+
+/// Log a formatted message on a [`DLog`](crate::DLog) at Error level, like [`DLog::e()`](crate::DLog::e).
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{dlog_e, DLog};
+/// 
+/// let durylog=DLog::new();
+/// let code=404;
+/// dlog_e!(durylog, "request failed with code {}", code);
+/// ```
+#[macro_export]
+macro_rules! dlog_e {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::dlog!($logger, $crate::Level::Error, $($arg)+)
+    };
+}
+
+/// Log a formatted message on a [`DLog`](crate::DLog) at Warn level, like [`DLog::w()`](crate::DLog::w).
+#[macro_export]
+macro_rules! dlog_w {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::dlog!($logger, $crate::Level::Warn, $($arg)+)
+    };
+}
+
+/// Log a formatted message on a [`DLog`](crate::DLog) at Info level, like [`DLog::i()`](crate::DLog::i).
+#[macro_export]
+macro_rules! dlog_i {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::dlog!($logger, $crate::Level::Info, $($arg)+)
+    };
+}
+
+/// Log a formatted message on a [`DLog`](crate::DLog) at Debug level, like [`DLog::d()`](crate::DLog::d).
+#[macro_export]
+macro_rules! dlog_d {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::dlog!($logger, $crate::Level::Debug, $($arg)+)
+    };
+}
+
+/// Log a formatted message on a [`DLog`](crate::DLog) at Trace level, like [`DLog::t()`](crate::DLog::t).
+#[macro_export]
+macro_rules! dlog_t {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::dlog!($logger, $crate::Level::Trace, $($arg)+)
+    };
+}
+
+/// Start a [`DTimer`](crate::DTimer) on a [`DLog`](crate::DLog) (or a [`DScope`](crate::DScope)) at a given level,
+/// with a formatted label. Keep the returned guard alive until the end of the measured code.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{dlog_timed, DLog, Level};
+/// 
+/// let durylog=DLog::new();
+/// let file="app.toml";
+/// let _timer=dlog_timed!(durylog, Level::Debug, "load {}", file);
+/// ```
+#[macro_export]
+macro_rules! dlog_timed {
+    ($logger:expr, $level:expr, $($arg:tt)+) => {
+        $logger.timed_at($level, &format!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DLog, DMemory, LevelFilter, tests::Counted};
+    use std::cell::Cell;
+
+    #[test]
+    fn format_only_enabled_levels() {
+        let memory=DMemory::new(10);
+        let mut durylog=DLog::new().without_console().with_sink(memory.clone());
+        durylog.with_level(LevelFilter::Info);
+
+        let count=Cell::new(0);
+        dlog_d!(durylog, "value={}", Counted(&count));
+        assert_eq!(count.get(), 0);
+        dlog_w!(durylog, "value={}", Counted(&count));
+        assert_eq!(count.get(), 1);
+        dlog_i!(durylog, "plain");
+        assert_eq!(memory.messages(), ["value=counted", "plain"]);
+    }
+}
@@ -0,0 +1,251 @@
+use log::Level;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ops::Deref,
+    sync::{Arc, Mutex, Once},
+};
+
+use crate::durylog::{DLog, DLogError, DRecord};
+use crate::sink::DSink;
+
+thread_local! {
+    /// Active captures of current thread.
+    static CAPTURES: RefCell<Vec<DMemory>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Global logger installation for captures.
+static CAPTURE_LOGGER: Once = Once::new();
+
+/// Sink that keeps the last records in memory, as structured entries.
+/// 
+/// It can be cloned: clones share the same records, so keep one to query records while the
+/// other is given to [`DLog::with_sink()`].
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{DLog, DMemory};
+/// use log::Level;
+/// 
+/// let memory=DMemory::new(100);
+/// let durylog=DLog::new().without_console().with_sink(memory.clone());
+/// 
+/// durylog.w("retrying");
+/// assert!(memory.contains(Level::Warn, "retrying"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DMemory {
+    /// Max number of records kept
+    capacity: usize,
+    /// Records, oldest first
+    records: Arc<Mutex<VecDeque<DRecord>>>,
+}
+
+impl DMemory {
+    /// Create a sink that keeps the last ['capacity'] records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Add ['record'], dropping the oldest one if capacity is reached.
+    pub(crate) fn push(&self, record: &DRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records=self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+    }
+
+    /// ['return'] a copy of all kept records, oldest first.
+    pub fn records(&self) -> Vec<DRecord> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// ['return'] records for which ['predicate'] is true.
+    pub fn find<F: Fn(&DRecord) -> bool>(&self, predicate: F) -> Vec<DRecord> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).iter().filter(|r| predicate(r)).cloned().collect()
+    }
+
+    /// ['return'] records of ['level'].
+    pub fn with_level(&self, level: Level) -> Vec<DRecord> {
+        self.find(|r| r.level == level)
+    }
+
+    /// ['return'] records whose target starts with ['target'].
+    pub fn with_target(&self, target: &str) -> Vec<DRecord> {
+        self.find(|r| r.target.starts_with(target))
+    }
+
+    /// ['return'] messages of all kept records, oldest first.
+    pub fn messages(&self) -> Vec<String> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|r| r.message.clone()).collect()
+    }
+
+    /// ['return'] true if a record of ['level'] whose message contains ['text'] has been kept.
+    pub fn contains(&self, level: Level, text: &str) -> bool {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|r| r.level == level && r.message.contains(text))
+    }
+
+    /// ['return'] number of kept records.
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// ['return'] true if no record is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all kept records.
+    pub fn clear(&self) {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+impl DSink for DMemory {
+    fn write(&self, record: &DRecord, _line: &str) -> Result<(), DLogError> {
+        self.push(record);
+        Ok(())
+    }
+}
+
+/// Records captured on current thread while alive (see [`capture()`]).
+/// 
+/// Derefs to [`DMemory`] for queries.
+#[derive(Debug)]
+pub struct DCapture {
+    /// Captured records
+    memory: DMemory,
+}
+
+impl Deref for DCapture {
+    type Target = DMemory;
+
+    fn deref(&self) -> &DMemory {
+        &self.memory
+    }
+}
+
+impl Drop for DCapture {
+    fn drop(&mut self) {
+        CAPTURES.with(|captures| {
+            let mut captures=captures.borrow_mut();
+            if let Some(pos) = captures.iter().rposition(|m| Arc::ptr_eq(&m.records, &self.memory.records)) {
+                captures.remove(pos);
+            }
+        });
+    }
+}
+
+/// Sink of the capture logger: dispatches records to captures of current thread.
+#[derive(Debug)]
+struct DThreadCapture;
+
+impl DSink for DThreadCapture {
+    fn write(&self, record: &DRecord, _line: &str) -> Result<(), DLogError> {
+        CAPTURES.with(|captures| {
+            for memory in captures.borrow().iter() {
+                memory.push(record);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Start capturing std::log records emitted on current thread, until the returned guard is dropped.
+/// 
+/// On first call a capture [`DLog`] is installed as global logger (nothing is printed); if another logger
+/// has already been installed, only records written with [`capture_sink()`] are captured.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::{assert_logged, warn};
+/// use log::Level;
+/// 
+/// let logs=durylog::capture();
+/// warn!("retrying in 5s");
+/// assert_logged!(Level::Warn, "retrying");
+/// assert_eq!(logs.len(), 1);
+/// ```
+pub fn capture() -> DCapture {
+    CAPTURE_LOGGER.call_once(|| {
+        DLog::new().without_console().with_sink(DThreadCapture).init_logger().ok();
+    });
+
+    let memory=DMemory::new(usize::MAX);
+    CAPTURES.with(|captures| captures.borrow_mut().push(memory.clone()));
+    DCapture { memory }
+}
+
+/// ['return'] a sink that sends records to captures of the thread that writes them, to capture
+/// records of a [`DLog`] used directly.
+pub fn capture_sink() -> impl DSink {
+    DThreadCapture
+}
+
+/// ['return'] true if a capture of current thread has a record of ['level'] whose message contains ['text'].
+#[doc(hidden)]
+pub fn captured_contains(level: Level, text: &str) -> bool {
+    CAPTURES.with(|captures| captures.borrow().iter().any(|m| m.contains(level, text)))
+}
+
+/// Assert that a record of a level, whose message contains a text, has been logged.
+/// 
+/// - `assert_logged!(level, text)` checks records captured on current thread (see [`capture()`]).
+/// - `assert_logged!(memory, level, text)` checks records kept in a [`DMemory`].
+#[macro_export]
+macro_rules! assert_logged {
+    ($level:expr, $text:expr) => {
+        assert!($crate::memory::captured_contains($level, $text), "no {} record containing {:?} has been logged", $level, $text)
+    };
+    ($memory:expr, $level:expr, $text:expr) => {
+        assert!($memory.contains($level, $text), "no {} record containing {:?} has been logged", $level, $text)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{info, warn};
+
+    #[test]
+    fn ring_keeps_last_records() {
+        let memory=DMemory::new(2);
+        let durylog=DLog::new().without_console().with_sink(memory.clone());
+        durylog.i("one");
+        durylog.w("two");
+        durylog.e("three");
+
+        assert_eq!(memory.messages(), ["two", "three"]);
+        assert_eq!(memory.with_level(Level::Error).len(), 1);
+        assert_logged!(memory, Level::Warn, "two");
+        memory.clear();
+        assert!(memory.is_empty());
+    }
+
+    #[test]
+    fn capture_is_scoped_to_thread() {
+        let logs=capture();
+        warn!(target: "my_app::net", attempt = 3; "retrying");
+        std::thread::spawn(|| info!("other thread")).join().unwrap();
+
+        assert_logged!(Level::Warn, "retrying");
+        assert_eq!(logs.len(), 1);
+        let record=&logs.with_target("my_app::net")[0];
+        assert_eq!(record.fields, [("attempt".to_string(), "3".to_string())]);
+    }
+
+    #[test]
+    fn capture_direct_api() {
+        let logs=capture();
+        let durylog=DLog::new().without_console().with_sink(capture_sink());
+        durylog.d("direct");
+        assert!(logs.contains(Level::Debug, "direct"));
+    }
+}
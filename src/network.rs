@@ -0,0 +1,505 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{atomic::{AtomicUsize, Ordering}, mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError}, Arc, OnceLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::{udp_connect, DSink};
+
+/// Default max number of lines kept in memory while disconnected.
+const DEFAULT_BUFFER_LIMIT: usize = 1000;
+/// Default first reconnection delay.
+const DEFAULT_MIN_BACKOFF: Duration = Duration::from_millis(500);
+/// Default max reconnection delay.
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Default connect and write timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Default max time [`DNetwork`] flush (and drop) waits for the worker thread.
+const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Network protocol used by [`DNetwork`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DNetProtocol {
+    /// Lines are sent on a tcp stream, terminated by '\n'.
+    Tcp,
+    /// Each line is sent in a udp datagram.
+    Udp,
+}
+
+/// What to do with a new line when the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DOverflow {
+    /// Drop the oldest buffered line (default). While the background thread is busy connecting or
+    /// writing, lines it has not received yet are never dropped: the new line is dropped instead.
+    DropOldest,
+    /// Drop the new line.
+    DropNewest,
+    /// Write the new line in [`DLog`](crate::DLog) own log file, if it is not already written there.
+    Fallback,
+}
+
+/// Settings of [`DNetwork`], moved in worker thread when it starts.
+#[derive(Debug, Clone)]
+struct DNetConfig {
+    /// Remote address
+    addr: String,
+    /// Protocol
+    protocol: DNetProtocol,
+    /// Max buffered lines
+    buffer_limit: usize,
+    /// Buffer overflow policy
+    overflow: DOverflow,
+    /// First reconnection delay
+    min_backoff: Duration,
+    /// Max reconnection delay
+    max_backoff: Duration,
+    /// Connect and write timeout
+    timeout: Duration,
+    /// Max time flush waits for the worker thread
+    flush_timeout: Duration,
+}
+
+/// Message for worker thread.
+#[derive(Debug)]
+enum DNetMsg {
+    /// A formatted line
+    Line(String),
+    /// Try to send buffered lines now, then acknowledge
+    Flush(Sender<()>),
+}
+
+/// An open connection.
+#[derive(Debug)]
+enum DConnection {
+    /// Tcp stream
+    Tcp(TcpStream),
+    /// Connected udp socket
+    Udp(UdpSocket),
+}
+
+/// Connection and buffer, owned by worker thread.
+#[derive(Debug)]
+struct DNetLink {
+    /// Current connection
+    connection: Option<DConnection>,
+    /// Lines waiting to be sent
+    buffer: VecDeque<String>,
+    /// Bytes of first buffered line already sent on current tcp stream
+    offset: usize,
+    /// Current reconnection delay
+    backoff: Duration,
+    /// Next reconnection attempt
+    retry_at: Instant,
+}
+
+/// Sink that streams log lines to a remote tcp or udp endpoint.
+/// Lines are formatted as in log file, following [`DLog`](crate::DLog) settings.
+/// 
+/// Lines are sent by a background thread, so name resolution, connection and slow writes never
+/// block the logging thread. While disconnected, lines are kept in memory and the thread tries to
+/// reconnect with exponential backoff. When the buffer is full, lines are dropped or written in
+/// [`DLog`](crate::DLog) own log file.
+/// 
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DLog, DNetwork, DNetProtocol, DOverflow};
+/// 
+/// let durylog=DLog::new()
+///     .with_file("app.log").unwrap()
+///     .with_sink(
+///         DNetwork::new("logs.example.com:5170", DNetProtocol::Tcp)
+///             .with_buffer_limit(10000)
+///             .with_overflow(DOverflow::Fallback)
+///     );
+/// ```
+#[derive(Debug)]
+pub struct DNetwork {
+    /// Settings
+    config: DNetConfig,
+    /// Channel to worker thread (started on first line)
+    sender: OnceLock<SyncSender<DNetMsg>>,
+    /// Number of lines queued or buffered, shared with worker thread
+    pending: Arc<AtomicUsize>,
+    /// Worker thread
+    worker: OnceLock<JoinHandle<()>>,
+}
+
+impl DNetwork {
+    /// Create a sink that sends to ['addr'] using ['protocol']. Connection is done on first write.
+    pub fn new(addr: &str, protocol: DNetProtocol) -> Self {
+        Self {
+            config: DNetConfig {
+                addr: addr.to_string(),
+                protocol,
+                buffer_limit: DEFAULT_BUFFER_LIMIT,
+                overflow: DOverflow::DropOldest,
+                min_backoff: DEFAULT_MIN_BACKOFF,
+                max_backoff: DEFAULT_MAX_BACKOFF,
+                timeout: DEFAULT_TIMEOUT,
+                flush_timeout: DEFAULT_FLUSH_TIMEOUT,
+            },
+            sender: OnceLock::new(),
+            pending: Arc::new(AtomicUsize::new(0)),
+            worker: OnceLock::new(),
+        }
+    }
+
+    /// Set max number of lines kept in memory while disconnected.
+    pub fn with_buffer_limit(mut self, limit: usize) -> Self {
+        self.config.buffer_limit=limit.max(1);
+        self
+    }
+
+    /// Set what to do when buffer is full. Default is [`DOverflow::DropOldest`].
+    pub fn with_overflow(mut self, overflow: DOverflow) -> Self {
+        self.config.overflow=overflow;
+        self
+    }
+
+    /// Set first and max reconnection delay. The delay doubles after each failed attempt.
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.config.min_backoff=min;
+        self.config.max_backoff=max.max(min);
+        self
+    }
+
+    /// Set connect and write timeout. Default is 1 second.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout=timeout;
+        self
+    }
+
+    /// Set max time flush (and drop) waits for buffered lines to be sent. Default is 5 seconds.
+    pub fn with_flush_timeout(mut self, timeout: Duration) -> Self {
+        self.config.flush_timeout=timeout;
+        self
+    }
+
+    /// ['return'] channel to worker thread, starting it if needed.
+    fn sender(&self) -> &SyncSender<DNetMsg> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver)=mpsc::sync_channel(self.config.buffer_limit);
+            let config=self.config.clone();
+            let pending=Arc::clone(&self.pending);
+            let spawned=thread::Builder::new()
+                .name(String::from("dlog-network"))
+                .spawn(move || worker(config, receiver, pending));
+            if let Ok(handle) = spawned {
+                self.worker.set(handle).ok();
+            }
+            sender
+        })
+    }
+
+    /// Ask worker thread to send buffered lines and wait at most flush timeout.
+    /// ['return'] true if worker is not started or has answered in time.
+    fn wait_flush(&self) -> bool {
+        let Some(sender) = self.sender.get() else { return true };
+        let (ack, done)=mpsc::channel();
+        match sender.try_send(DNetMsg::Flush(ack)) {
+            Ok(()) => done.recv_timeout(self.config.flush_timeout).is_ok(),
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Disconnected(_)) => true,
+        }
+    }
+
+    /// ['return'] result of refusing a new line, following overflow policy.
+    fn overflow(&self) -> Result<(), DLogError> {
+        match self.config.overflow {
+            DOverflow::Fallback => Err(DLogError::Overflow),
+            DOverflow::DropOldest | DOverflow::DropNewest => Ok(()),
+        }
+    }
+}
+
+impl DSink for DNetwork {
+    fn write(&self, _record: &DRecord, line: &str) -> Result<(), DLogError> {
+        // With DropOldest the worker makes room, other policies refuse the new line
+        let full=self.pending.load(Ordering::Relaxed) >= self.config.buffer_limit;
+        if full && self.config.overflow != DOverflow::DropOldest {
+            return self.overflow();
+        }
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        match self.sender().try_send(DNetMsg::Line(line.to_string())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                // Worker is stuck on a write: the new line cannot be kept whatever the policy
+                self.pending.fetch_sub(1, Ordering::Relaxed);
+                self.overflow()
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                self.pending.fetch_sub(1, Ordering::Relaxed);
+                Err(DLogError::Err(io::Error::new(io::ErrorKind::BrokenPipe, "network worker stopped")))
+            },
+        }
+    }
+
+    fn flush(&self) {
+        self.wait_flush();
+    }
+}
+
+impl Drop for DNetwork {
+    fn drop(&mut self) {
+        // Closing the channel makes worker try a last send and exit; join it only if it is idle,
+        // to never block longer than flush timeout
+        let idle=self.wait_flush();
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if idle {
+                worker.join().ok();
+            }
+        }
+    }
+}
+
+/// Worker thread: buffers lines and sends them, reconnecting with backoff.
+fn worker(config: DNetConfig, receiver: Receiver<DNetMsg>, pending: Arc<AtomicUsize>) {
+    let mut link=DNetLink {
+        connection: None,
+        buffer: VecDeque::new(),
+        offset: 0,
+        backoff: config.min_backoff,
+        retry_at: Instant::now(),
+    };
+    loop {
+        let msg=if link.buffer.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(link.retry_at.saturating_duration_since(Instant::now()))
+        };
+
+        match msg {
+            Ok(DNetMsg::Line(line)) => {
+                link.push(&config, line, &pending);
+                link.send(&config, &pending);
+            },
+            Ok(DNetMsg::Flush(ack)) => {
+                link.send(&config, &pending);
+                if let Some(DConnection::Tcp(stream)) = link.connection.as_mut() {
+                    stream.flush().ok();
+                }
+                ack.send(()).ok();
+            },
+            Err(RecvTimeoutError::Timeout) => link.send(&config, &pending),
+            Err(RecvTimeoutError::Disconnected) => {
+                if !link.buffer.is_empty() {
+                    link.send(&config, &pending);
+                }
+                break;
+            },
+        }
+    }
+}
+
+impl DNetLink {
+    /// Add ['line'] to buffer, dropping the oldest line if it is full.
+    fn push(&mut self, config: &DNetConfig, line: String, pending: &AtomicUsize) {
+        if self.buffer.len() >= config.buffer_limit {
+            // Never drop a partially sent line
+            let oldest=if self.offset > 0 {1} else {0};
+            pending.fetch_sub(1, Ordering::Relaxed);
+            if self.buffer.remove(oldest).is_none() {
+                return;
+            }
+        }
+        self.buffer.push_back(line);
+    }
+
+    /// Send buffered lines, connecting if needed and allowed by backoff.
+    fn send(&mut self, config: &DNetConfig, pending: &AtomicUsize) {
+        if self.connection.is_none() {
+            if Instant::now() < self.retry_at {
+                return;
+            }
+            match connect(config) {
+                Ok(connection) => {
+                    self.connection=Some(connection);
+                    self.offset=0;
+                    self.backoff=config.min_backoff;
+                },
+                Err(_) => {
+                    self.retry_later(config);
+                    return;
+                },
+            }
+        }
+
+        while let Some(line) = self.buffer.front() {
+            let result=match self.connection.as_mut() {
+                Some(DConnection::Tcp(stream)) => write_from(stream, format!("{}\n", line).as_bytes(), &mut self.offset),
+                Some(DConnection::Udp(socket)) => socket.send(line.as_bytes()).map(|_| ()),
+                None => break,
+            };
+            if let Err(err) = result {
+                // Keep line: on timeout continue later on same stream, else reconnect and send it again
+                if !matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) {
+                    self.connection=None;
+                    self.offset=0;
+                    self.retry_later(config);
+                }
+                return;
+            }
+            self.buffer.pop_front();
+            self.offset=0;
+            pending.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Schedule next reconnection and increase backoff.
+    fn retry_later(&mut self, config: &DNetConfig) {
+        self.retry_at=Instant::now() + self.backoff;
+        self.backoff=(self.backoff * 2).min(config.max_backoff);
+    }
+}
+
+/// Open a new connection.
+fn connect(config: &DNetConfig) -> io::Result<DConnection> {
+    let addrs: Vec<SocketAddr>=config.addr.to_socket_addrs()?.collect();
+    let mut last_err=io::Error::new(io::ErrorKind::NotFound, "no address");
+    for addr in addrs {
+        let result=match config.protocol {
+            DNetProtocol::Tcp => TcpStream::connect_timeout(&addr, config.timeout).and_then(|stream| {
+                stream.set_write_timeout(Some(config.timeout))?;
+                Ok(DConnection::Tcp(stream))
+            }),
+            DNetProtocol::Udp => udp_connect(addr).map(DConnection::Udp),
+        };
+        match result {
+            Ok(connection) => return Ok(connection),
+            Err(err) => last_err=err,
+        }
+    }
+    Err(last_err)
+}
+
+/// Write ['data'] on ['stream'] starting at ['offset'], which is advanced by bytes written.
+fn write_from(stream: &mut TcpStream, data: &[u8], offset: &mut usize) -> io::Result<()> {
+    while *offset < data.len() {
+        match stream.write(&data[*offset..]) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => *offset+=written,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DLog;
+    use log::Level;
+    use std::{io::{BufRead, BufReader}, net::TcpListener};
+
+    /// ['return'] an address where nobody is listening.
+    fn closed_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    #[test]
+    fn tcp_lines() {
+        let server=TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink=DNetwork::new(&server.local_addr().unwrap().to_string(), DNetProtocol::Tcp);
+        let record=DRecord::new(Level::Info, "");
+        sink.write(&record, "first").unwrap();
+        sink.write(&record, "second").unwrap();
+
+        let (conn, _)=server.accept().unwrap();
+        let mut lines=BufReader::new(conn).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "first");
+        assert_eq!(lines.next().unwrap().unwrap(), "second");
+        assert_eq!(sink.worker.get().unwrap().thread().name(), Some("dlog-network"));
+    }
+
+    #[test]
+    fn reconnect_sends_buffered_lines() {
+        let addr=closed_addr();
+        let sink=DNetwork::new(&addr.to_string(), DNetProtocol::Tcp).with_backoff(Duration::ZERO, Duration::ZERO);
+        let record=DRecord::new(Level::Info, "");
+        sink.write(&record, "buffered").unwrap();
+        sink.flush();
+
+        let server=TcpListener::bind(addr).unwrap();
+        sink.write(&record, "live").unwrap();
+
+        let (conn, _)=server.accept().unwrap();
+        let mut lines=BufReader::new(conn).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "buffered");
+        assert_eq!(lines.next().unwrap().unwrap(), "live");
+    }
+
+    #[test]
+    fn write_does_not_wait_for_connection() {
+        // Non routable address: connecting waits for the timeout, or fails at once without network
+        let sink=DNetwork::new("10.255.255.1:5170", DNetProtocol::Tcp)
+            .with_timeout(Duration::from_secs(5))
+            .with_flush_timeout(Duration::from_millis(100));
+        let record=DRecord::new(Level::Info, "");
+        let start=Instant::now();
+        for line in ["one", "two", "three"] {
+            sink.write(&record, line).unwrap();
+        }
+        sink.flush();
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn overflow_to_dlog_file() {
+        let path=std::env::temp_dir().join(format!("durylog-network-{}.log", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let sink=DNetwork::new(&closed_addr().to_string(), DNetProtocol::Tcp)
+            .with_buffer_limit(1)
+            .with_overflow(DOverflow::Fallback);
+        sink.write(&DRecord::new(Level::Info, ""), "full").unwrap();
+        assert!(matches!(sink.write(&DRecord::new(Level::Info, ""), "full"), Err(DLogError::Overflow)));
+
+        let mut durylog=DLog::new().without_console().with_timestamp_format("T")
+            .with_file(path.to_str().unwrap()).unwrap()
+            .with_sink(sink);
+        durylog.i("in file");
+        durylog.enable_file(false);
+        durylog.i("fallback");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "T : INFO   : in file\nT : INFO   : fallback\n");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn partial_write_resumes_at_offset() {
+        let server=TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream=TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        // Simulate a line interrupted by a timeout after 3 bytes
+        let mut offset=3;
+        write_from(&mut stream, b"second\n", &mut offset).unwrap();
+        assert_eq!(offset, 7);
+
+        let (conn, _)=server.accept().unwrap();
+        let mut lines=BufReader::new(conn).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "ond");
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let addr=closed_addr();
+        let sink=DNetwork::new(&addr.to_string(), DNetProtocol::Tcp)
+            .with_backoff(Duration::ZERO, Duration::ZERO)
+            .with_buffer_limit(2);
+        let record=DRecord::new(Level::Info, "");
+        for line in ["one", "two", "three"] {
+            sink.write(&record, line).unwrap();
+            sink.flush();
+        }
+
+        let server=TcpListener::bind(addr).unwrap();
+        sink.flush();
+        drop(sink);
+        let (conn, _)=server.accept().unwrap();
+        let lines: Vec<String>=BufReader::new(conn).lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines, ["two", "three"]);
+    }
+}
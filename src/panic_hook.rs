@@ -0,0 +1,69 @@
+use log::{Level, Record};
+use std::{backtrace::Backtrace, panic, thread};
+
+/// Target of panic records.
+pub const PANIC_TARGET: &str = "panic";
+
+/// Install a panic hook that writes panics as Error records through the std::log logger, flushes it,
+/// then runs the previous hook (the default one prints the panic on stderr).
+/// 
+/// The record has target [`PANIC_TARGET`], the panic location as source file and line, and fields
+/// `thread` and `location`. If ['backtrace'] is true, a backtrace is appended to the message.
+pub(crate) fn install(backtrace: bool) {
+    let previous_hook=panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread=thread::current();
+        let thread_name=thread.name().unwrap_or("<unnamed>");
+        let payload=info.payload().downcast_ref::<&str>().copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let location=info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())).unwrap_or_default();
+
+        let mut message=format!("thread '{}' panicked at {}: {}", thread_name, location, payload);
+        if backtrace {
+            message.push_str("\nstack backtrace:\n");
+            message.push_str(&Backtrace::force_capture().to_string());
+        }
+
+        let fields=[("thread", thread_name), ("location", location.as_str())];
+        log::logger().log(&Record::builder()
+            .level(Level::Error)
+            .target(PANIC_TARGET)
+            .file(info.location().map(|l| l.file()))
+            .line(info.location().map(|l| l.line()))
+            .key_values(&fields)
+            .args(format_args!("{}", message))
+            .build());
+        log::logger().flush();
+
+        previous_hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_is_logged() {
+        let previous_hook=panic::take_hook();
+        install(true);
+        // Captures are per thread: capture in the panicking thread and hand its records over
+        let (sender, receiver)=std::sync::mpsc::channel();
+        let result=thread::Builder::new().name("worker".to_string()).spawn(move || {
+            let logs=crate::capture();
+            sender.send(logs.clone()).unwrap();
+            panic!("boom");
+        }).unwrap().join();
+        panic::set_hook(previous_hook);
+        assert!(result.is_err());
+        let logs=receiver.recv().unwrap();
+
+        let record=&logs.with_target(PANIC_TARGET)[0];
+        assert_eq!(record.level, Level::Error);
+        assert!(record.message.starts_with("thread 'worker' panicked at "), "{}", record.message);
+        assert!(record.message.contains(": boom\nstack backtrace:\n"), "{}", record.message);
+        assert_eq!(record.file.as_deref(), Some(file!()));
+        assert_eq!(record.fields[0], ("thread".to_string(), "worker".to_string()));
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::SecondsFormat;
+use std::{fmt::Debug, fs, io, net::{ToSocketAddrs, UdpSocket}};
+
+use crate::durylog::{DLogError, DRecord};
+
+/// An additional output for [`DLog`](crate::DLog), besides console and file.
+/// 
+/// Sinks are added with [`DLog::with_sink()`](crate::DLog::with_sink) and receive every record
+/// that passes filters, after redaction.
+pub trait DSink: Send + Sync + Debug {
+    /// Write ['record']. ['line'] is the record formatted as in log file (timestamp, level, message).
+    /// Return [`DLogError::Overflow`] to have ['line'] written in [`DLog`](crate::DLog) own log file instead.
+    fn write(&self, record: &DRecord, line: &str) -> Result<(), DLogError>;
+
+    /// Flush any buffered record.
+    fn flush(&self) {}
+}
+
+/// ['return'] name of this host, or "localhost" if it cannot be read.
+pub(crate) fn hostname() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"].iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .chain(["HOSTNAME", "COMPUTERNAME"].iter().filter_map(|var| std::env::var(var).ok()))
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("localhost"))
+}
+
+/// ['return'] an udp socket connected to first reachable address of ['addr'], bound on the same family (IPv4 or IPv6).
+pub(crate) fn udp_connect<A: ToSocketAddrs>(addr: A) -> io::Result<UdpSocket> {
+    let mut last_err=io::Error::new(io::ErrorKind::NotFound, "no address");
+    for addr in addr.to_socket_addrs()? {
+        let result=UdpSocket::bind(if addr.is_ipv4() {"0.0.0.0:0"} else {"[::]:0"}).and_then(|socket| {
+            socket.connect(addr)?;
+            Ok(socket)
+        });
+        match result {
+            Ok(socket) => return Ok(socket),
+            Err(err) => last_err=err,
+        }
+    }
+    Err(last_err)
+}
+
+/// ['return'] current executable name, used as default app name, or "-" if it cannot be read.
+pub(crate) fn app_name() -> String {
+    std::env::current_exe().ok()
+        .and_then(|path| path.file_stem().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| String::from("-"))
+}
+
+/// ['return'] message of ['record'] followed by its fields as ' key=value'.
+pub(crate) fn message_with_fields(record: &DRecord) -> String {
+    let mut text=record.message.clone();
+    for (key, value) in &record.fields {
+        text.push(' ');
+        text.push_str(key);
+        text.push('=');
+        text.push_str(value);
+    }
+    text
+}
+
+/// ['return'] ['s'] as a quoted json string.
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out=String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// ['return'] ['record'] as a json object: timestamp, level, target, file, line, message and fields.
+pub(crate) fn record_json(record: &DRecord) -> String {
+    let mut json=String::from("{");
+    json+=&format!("\"timestamp\":{}", json_str(&record.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)));
+    json+=&format!(",\"level\":{}", json_str(record.level.as_str()));
+    if !record.target.is_empty() {
+        json+=&format!(",\"target\":{}", json_str(&record.target));
+    }
+    if let Some(file) = &record.file {
+        json+=&format!(",\"file\":{}", json_str(file));
+    }
+    if let Some(line) = record.line {
+        json+=&format!(",\"line\":{}", line);
+    }
+    json+=&format!(",\"message\":{}", json_str(&record.message));
+    if !record.fields.is_empty() {
+        json+=",\"fields\":{";
+        json+=&record.fields.iter()
+            .map(|(key, value)| format!("{}:{}", json_str(key), json_str(value)))
+            .collect::<Vec<String>>()
+            .join(",");
+        json.push('}');
+    }
+    json.push('}');
+    json
+}
+
+/// ['return'] ['record'] as a logfmt line: time, level, target, msg and fields.
+pub(crate) fn record_logfmt(record: &DRecord) -> String {
+    let mut line=format!("time={} level={}",
+        record.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+        record.level.as_str().to_lowercase());
+    if !record.target.is_empty() {
+        line+=&format!(" target={}", logfmt_value(&record.target));
+    }
+    line+=&format!(" msg={}", logfmt_value(&record.message));
+    for (key, value) in &record.fields {
+        let key: String=key.chars().map(|c| if c.is_whitespace() || c == '=' || c == '"' { '_' } else { c }).collect();
+        line+=&format!(" {}={}", key, logfmt_value(value));
+    }
+    line
+}
+
+/// ['return'] ['value'] quoted and escaped if needed by logfmt.
+fn logfmt_value(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == '"') {
+        return value.to_string();
+    }
+    json_str(value)
+}
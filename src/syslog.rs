@@ -0,0 +1,273 @@
+use chrono::{Local, SecondsFormat};
+use log::Level;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::{
+    io::Write,
+    net::{TcpStream, ToSocketAddrs, UdpSocket},
+    path::Path,
+    process,
+    sync::Mutex,
+};
+
+use crate::durylog::{DLogError, DRecord};
+use crate::sink::{app_name, hostname, message_with_fields, udp_connect, DSink};
+
+/// Default syslog socket on Linux.
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Syslog message format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DSyslogFormat {
+    /// BSD syslog: `<PRI>Mmm dd hh:mm:ss HOST APP[PID]: MSG`.
+    Rfc3164,
+    /// IETF syslog: `<PRI>1 TIMESTAMP HOST APP PID - - MSG` (default).
+    Rfc5424,
+}
+
+/// Syslog facility.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DSyslogFacility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Where syslog messages are sent.
+#[derive(Debug)]
+enum DSyslogTransport {
+    /// Local unix datagram socket (e.g. /dev/log)
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    /// Udp socket connected to server
+    Udp(UdpSocket),
+    /// Tcp stream (octet counting framing)
+    Tcp(Mutex<TcpStream>),
+}
+
+/// Sink that sends records to a syslog daemon.
+/// 
+/// ### Example
+/// ```rust,no_run
+/// use durylog::{DLog, DSyslog, DSyslogFacility};
+/// 
+/// let durylog=DLog::new()
+///     .with_sink(
+///         DSyslog::unix("/dev/log").unwrap()
+///             .with_facility(DSyslogFacility::Local0)
+///             .with_app_name("my_app")
+///     );
+/// ```
+#[derive(Debug)]
+pub struct DSyslog {
+    /// Message format
+    format: DSyslogFormat,
+    /// Facility
+    facility: DSyslogFacility,
+    /// Application name (tag)
+    app_name: String,
+    /// Host name
+    hostname: String,
+    /// Process id
+    pid: u32,
+    /// Socket
+    transport: DSyslogTransport,
+}
+
+impl DSyslog {
+    /// Create a sink that sends to local unix datagram socket ['path'] (usually [`SYSLOG_SOCKET`]).
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> Result<Self, DLogError> {
+        let socket=UnixDatagram::unbound().map_err(DLogError::Err)?;
+        socket.connect(path).map_err(DLogError::Err)?;
+        Ok(Self::with_transport(DSyslogTransport::Unix(socket)))
+    }
+
+    /// Create a sink that sends to udp ['addr'] (e.g. "127.0.0.1:514").
+    pub fn udp<A: ToSocketAddrs>(addr: A) -> Result<Self, DLogError> {
+        let socket=udp_connect(addr).map_err(DLogError::Err)?;
+        Ok(Self::with_transport(DSyslogTransport::Udp(socket)))
+    }
+
+    /// Create a sink that sends to tcp ['addr'] (e.g. "127.0.0.1:601").
+    pub fn tcp<A: ToSocketAddrs>(addr: A) -> Result<Self, DLogError> {
+        let stream=TcpStream::connect(addr).map_err(DLogError::Err)?;
+        Ok(Self::with_transport(DSyslogTransport::Tcp(Mutex::new(stream))))
+    }
+
+    /// Create a sink with default settings on ['transport'].
+    fn with_transport(transport: DSyslogTransport) -> Self {
+        Self {
+            format: DSyslogFormat::Rfc5424,
+            facility: DSyslogFacility::User,
+            app_name: app_name(),
+            hostname: hostname(),
+            pid: process::id(),
+            transport,
+        }
+    }
+
+    /// Set message format. Default is [`DSyslogFormat::Rfc5424`].
+    pub fn with_format(mut self, format: DSyslogFormat) -> Self {
+        self.format=format;
+        self
+    }
+
+    /// Set facility. Default is [`DSyslogFacility::User`].
+    pub fn with_facility(mut self, facility: DSyslogFacility) -> Self {
+        self.facility=facility;
+        self
+    }
+
+    /// Set application name. Default is current executable name.
+    pub fn with_app_name(mut self, app_name: &str) -> Self {
+        self.app_name=app_name.to_string();
+        self
+    }
+
+    /// Set host name. Default is this host name.
+    pub fn with_hostname(mut self, hostname: &str) -> Self {
+        self.hostname=hostname.to_string();
+        self
+    }
+
+    /// ['return'] ['record'] formatted as syslog message.
+    fn format(&self, record: &DRecord) -> String {
+        let pri=(self.facility as u8) * 8 + severity(record.level);
+        let msg=message_with_fields(record);
+        match self.format {
+            DSyslogFormat::Rfc3164 => format!("<{}>{} {} {}[{}]: {}",
+                pri,
+                record.timestamp.with_timezone(&Local).format("%b %e %H:%M:%S"),
+                self.hostname,
+                self.app_name,
+                self.pid,
+                msg
+            ),
+            DSyslogFormat::Rfc5424 => format!("<{}>1 {} {} {} {} - - {}",
+                pri,
+                record.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+                self.hostname,
+                self.app_name,
+                self.pid,
+                msg
+            ),
+        }
+    }
+}
+
+impl DSink for DSyslog {
+    fn write(&self, record: &DRecord, _line: &str) -> Result<(), DLogError> {
+        let msg=self.format(record);
+        match &self.transport {
+            #[cfg(unix)]
+            DSyslogTransport::Unix(socket) => socket.send(msg.as_bytes()).map(|_| ()),
+            DSyslogTransport::Udp(socket) => socket.send(msg.as_bytes()).map(|_| ()),
+            DSyslogTransport::Tcp(stream) => {
+                let mut stream=stream.lock().unwrap_or_else(|e| e.into_inner());
+                write!(stream, "{} {}", msg.len(), msg)
+            },
+        }.map_err(DLogError::Err)
+    }
+}
+
+/// ['return'] syslog severity of ['level'].
+pub(crate) fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error    => 3, // err
+        Level::Warn     => 4, // warning
+        Level::Info     => 6, // info
+        Level::Debug    => 7, // debug
+        Level::Trace    => 7, // debug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Read, net::TcpListener};
+
+    #[test]
+    fn udp_rfc5424() {
+        let server=UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink=DSyslog::udp(server.local_addr().unwrap()).unwrap()
+            .with_facility(DSyslogFacility::Local0)
+            .with_app_name("test")
+            .with_hostname("host");
+
+        sink.write(&DRecord::new(Level::Warn, "disk full"), "").unwrap();
+
+        let mut buf=[0; 1024];
+        let len=server.recv(&mut buf).unwrap();
+        let msg=String::from_utf8_lossy(&buf[..len]);
+        assert!(msg.starts_with("<132>1 "), "{}", msg);
+        assert!(msg.ends_with(&format!(" host test {} - - disk full", process::id())), "{}", msg);
+    }
+
+    #[test]
+    fn udp_ipv6() {
+        // Skip where IPv6 loopback is not available
+        let Ok(server)=UdpSocket::bind("[::1]:0") else { return };
+        let sink=DSyslog::udp(server.local_addr().unwrap()).unwrap();
+        sink.write(&DRecord::new(Level::Info, "ready"), "").unwrap();
+
+        let mut buf=[0; 1024];
+        let len=server.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with(" ready"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_rfc3164() {
+        let path=std::env::temp_dir().join(format!("durylog-syslog-{}.sock", process::id()));
+        std::fs::remove_file(&path).ok();
+        let server=UnixDatagram::bind(&path).unwrap();
+        let sink=DSyslog::unix(&path).unwrap()
+            .with_format(DSyslogFormat::Rfc3164)
+            .with_app_name("test")
+            .with_hostname("host");
+
+        sink.write(&DRecord::new(Level::Error, "failed"), "").unwrap();
+
+        let mut buf=[0; 1024];
+        let len=server.recv(&mut buf).unwrap();
+        let msg=String::from_utf8_lossy(&buf[..len]);
+        assert!(msg.starts_with("<11>"), "{}", msg);
+        assert!(msg.ends_with(&format!(" host test[{}]: failed", process::id())), "{}", msg);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn tcp_octet_counting() {
+        let server=TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink=DSyslog::tcp(server.local_addr().unwrap()).unwrap().with_hostname("host");
+        let (mut conn, _)=server.accept().unwrap();
+
+        sink.write(&DRecord::new(Level::Info, "hello"), "").unwrap();
+        drop(sink);
+
+        let mut received=String::new();
+        conn.read_to_string(&mut received).unwrap();
+        let (len, msg)=received.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), msg.len());
+        assert!(msg.starts_with("<14>1 "), "{}", msg);
+    }
+}
@@ -0,0 +1,100 @@
+use log::Level;
+use std::{panic::Location, time::{Duration, Instant}};
+use crate::DScope;
+
+/// Guard returned by [`DLog::timed()`](crate::DLog::timed): logs its label when created and the elapsed time when
+/// dropped.
+/// 
+/// ### Example
+/// ```rust
+/// use durylog::DLog;
+/// use std::time::Duration;
+/// 
+/// let durylog=DLog::new();
+/// {
+///     let _timer=durylog.timed("load config").with_threshold(Duration::from_millis(500));
+///     // 2023/01/02 18.01.27 : INFO   : start load config
+/// }
+/// // 2023/01/02 18.01.27 : INFO   : end load config took 0.012ms
+/// ```
+#[derive(Debug)]
+#[must_use = "elapsed time is logged when the guard is dropped"]
+pub struct DTimer {
+    /// Logger to write to
+    scope: DScope,
+    /// Level of start and end records
+    level: Level,
+    /// Measured operation
+    label: String,
+    /// Start time
+    start: Instant,
+    /// Elapsed time over it escalates end record to Warn
+    threshold: Option<Duration>,
+    /// Where the timer has been created, used as call site of both records
+    location: &'static Location<'static>,
+}
+
+impl DTimer {
+    /// Create a timer for ['label'] and log its start on ['level'] with ['scope'].
+    #[track_caller]
+    pub(crate) fn new(scope: DScope, level: Level, label: &str) -> Self {
+        let location=Location::caller();
+        scope.log_at(level, format_args!("start {}", label), location);
+        Self {
+            scope,
+            level,
+            label: label.to_string(),
+            start: Instant::now(),
+            threshold: None,
+            location,
+        }
+    }
+
+    /// Log end record on Warn level (or Error if timer level is Error) when elapsed time exceeds ['threshold'].
+    pub fn with_threshold(mut self, threshold: Duration) -> Self {
+        self.threshold=Some(threshold);
+        self
+    }
+
+    /// ['return'] time elapsed since timer creation.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for DTimer {
+    fn drop(&mut self) {
+        let elapsed=self.elapsed();
+        let level=match self.threshold {
+            Some(threshold) if elapsed > threshold => self.level.min(Level::Warn),
+            _ => self.level,
+        };
+        self.scope.log_at(level, format_args!("end {} took {:.3}ms", self.label, elapsed.as_secs_f64() * 1000.0), self.location);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DLog, DMemory};
+    use log::Level;
+    use std::time::Duration;
+
+    #[test]
+    fn logs_start_and_elapsed() {
+        let memory=DMemory::new(10);
+        let durylog=DLog::new().without_console().with_sink(memory.clone());
+
+        drop(durylog.timed_at(Level::Debug, "fast").with_threshold(Duration::from_secs(60)));
+        drop(durylog.child("db").timed("slow").with_threshold(Duration::ZERO));
+
+        let records=memory.records();
+        assert_eq!((records[0].level, records[0].message.as_str()), (Level::Debug, "start fast"));
+        assert_eq!(records[1].level, Level::Debug);
+        assert!(records[1].message.starts_with("end fast took ") && records[1].message.ends_with("ms"));
+        assert_eq!((records[2].level, records[2].target.as_str()), (Level::Info, "db"));
+        assert_eq!(records[3].level, Level::Warn);
+        // Both records have the call site of the timer
+        assert_eq!(records[3].file.as_deref(), Some(file!()));
+        assert_eq!(records[2].line, records[3].line);
+    }
+}